@@ -152,9 +152,8 @@ impl App {
     }
 
     fn handle_crossterm_events(&mut self, event: Event, page: &mut Route) {
-        match event {
-            Event::Key(event) => page.handle_key_event(event),
-            _ => {}
+        if let Event::Key(event) = event {
+            page.handle_key_event(event)
         }
     }
 }
@@ -2,6 +2,7 @@ use ratatui::Frame;
 use ratatui::crossterm::event::KeyEvent;
 use std::sync::Arc;
 
+#[allow(clippy::module_inception)]
 mod app;
 mod router;
 
@@ -0,0 +1,794 @@
+use crate::cli::monitor::{self, Monitor};
+use crate::cli::{ChipArgs, ChipFormat, RunArgs, SourceArgs};
+use serde::Serialize;
+use serde_json::json;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use tec2_parser::Severity;
+use tec2_parser::asm::{Assembly, InstructionSet};
+use tec2_parser::ast::token::Identifier;
+use tec2_parser::compile::{Step, compile};
+use tec2_parser::parser::decoder::Decoder;
+use tec2_parser::parser::format::{OutputMode, WordFormat};
+use tec2_parser::parser::instrument::{Ci, Instrument};
+use tec2_parser::parser::layout::Layout;
+use tec2_parser::program::{
+    ControlFlow, Decompiled, ImageFormat, ListingFormat, MemoryFormat, Program, parse_number,
+};
+use tec2_parser::result::{Encoding, FieldState, ParseResult};
+use tec2_parser::sim::{Datapath, Machine, PC, Sequencer, Signals};
+
+pub fn print_json<T: Serialize>(value: &T) {
+    println!("{}", serde_json::to_string_pretty(value).unwrap());
+}
+
+pub fn encode(
+    expr: &str,
+    output_mode: &OutputMode,
+    scratch: &[Identifier],
+    json: bool,
+) -> Result<(), String> {
+    let mut result = ParseResult::parse(expr);
+    // 一条微指令完成不了时拆分
+    if !result.is_ok() {
+        match compile(expr, scratch) {
+            Ok(steps) => {
+                print_steps(expr, &steps, output_mode, json);
+                return Ok(());
+            }
+            // 连续运算不是一条微指令的语法，给出拆分时的错误
+            Err(error @ ParseResult::ExprParseError { .. }) => result = error,
+            Err(_) => {}
+        }
+    }
+    if json {
+        print_json(&result.report(expr));
+    }
+    match &result {
+        ParseResult::Result(encoding) => {
+            if !json {
+                println!("{}", output_mode.format(&encoding.hex));
+                for diagnostic in result.diagnostics(expr) {
+                    eprintln!("{}", diagnostic);
+                }
+            }
+            Ok(())
+        }
+        error => Err(error.to_string()),
+    }
+}
+
+/// 拆成多条微指令时的JSON，每条带上对应的表达式和诊断信息
+pub fn steps_report(expr: &str, steps: &[Step]) -> serde_json::Value {
+    json!({
+        "input": expr,
+        "ok": true,
+        "steps": steps
+            .iter()
+            .map(|step| {
+                let diagnostics = step.encoding.lints.iter();
+                json!({
+                    "source": step.source,
+                    "diagnostics": diagnostics
+                        .map(|lint| lint.diagnostic(&step.source))
+                        .collect::<Vec<_>>(),
+                    "encoding": step.encoding,
+                })
+            })
+            .collect::<Vec<_>>(),
+    })
+}
+
+/// 拆成多条微指令时每行输出一条，后面加上对应的表达式
+pub fn print_steps(expr: &str, steps: &[Step], output_mode: &OutputMode, json: bool) {
+    if json {
+        print_json(&steps_report(expr, steps));
+        return;
+    }
+    for step in steps.iter() {
+        match steps.len() {
+            1 => println!("{}", output_mode.format(&step.encoding.hex)),
+            _ => println!(
+                "{}\t{}",
+                output_mode.format(&step.encoding.hex),
+                step.source
+            ),
+        }
+        for lint in step.encoding.lints.iter() {
+            eprintln!("{}", lint.diagnostic(&step.source));
+        }
+    }
+}
+
+pub fn decode(hex: &str, format: &WordFormat, json: bool) -> Result<(), String> {
+    let decoder = Decoder::from_words(hex, format, Layout::active()).map_err(|error| {
+        if json {
+            print_json(&json!({ "input": hex, "ok": false, "message": error.to_string() }));
+        }
+        error.to_string()
+    })?;
+    match json {
+        true => print_json(&json!({
+            "input": hex,
+            "ok": true,
+            "encoding": Encoding::from_decoder(&decoder),
+        })),
+        false => print_fields(&Encoding::from_decoder(&decoder), false),
+    }
+    Ok(())
+}
+
+/// 一个周期完成不了的表达式按--scratch拆成多条微指令，没有指定时用Q暂存
+fn load_program(source: &SourceArgs, json: bool) -> Result<Program, String> {
+    let file = source.file.as_path();
+    let text =
+        std::fs::read_to_string(file).map_err(|error| format!("{}: {}", file.display(), error))?;
+    let program = Program::compile(&text, &source.scratch.registers)
+        .and_then(|program| program.image().map(|_| program));
+    program.map_err(|error| {
+        if json {
+            print_json(&json!({ "ok": false, "message": error.to_string(), "error": error }));
+        }
+        format!("{}: {}", file.display(), error)
+    })
+}
+
+pub fn build(
+    source: &SourceArgs,
+    output: Option<&Path>,
+    output_mode: &OutputMode,
+    json: bool,
+) -> Result<(), String> {
+    let program = load_program(source, json)?;
+    let content = match json {
+        true => serde_json::to_string_pretty(&json!({
+            "ok": true,
+            "instructions": program.instructions(),
+        }))
+        .unwrap(),
+        false => program
+            .image()
+            .unwrap()
+            .iter()
+            .map(|(address, hex)| format!("{:03X}: {}\n", address, output_mode.format(hex)))
+            .collect::<String>(),
+    };
+    match output {
+        Some(output) => std::fs::write(output, content)
+            .map_err(|error| format!("{}: {}", output.display(), error)),
+        None => {
+            print!("{}", content);
+            Ok(())
+        }
+    }
+}
+
+pub fn listing(
+    source: &SourceArgs,
+    format: ListingFormat,
+    output: Option<&Path>,
+    json: bool,
+) -> Result<(), String> {
+    let program = load_program(source, json)?;
+    let content = program.listing(format);
+    match output {
+        Some(output) => {
+            std::fs::write(output, content)
+                .map_err(|error| format!("{}: {}", output.display(), error))?;
+            match json {
+                true => print_json(&json!({ "ok": true, "file": output })),
+                false => println!("{}", output.display()),
+            }
+        }
+        None if json => print_json(&json!({ "ok": true, "listing": content })),
+        None => print!("{}", content),
+    }
+    Ok(())
+}
+
+pub fn csv(source: &SourceArgs, output: Option<&Path>, json: bool) -> Result<(), String> {
+    let program = load_program(source, json)?;
+    let output = output.map_or_else(|| source.file.with_extension("csv"), Path::to_path_buf);
+    std::fs::write(&output, program.to_csv())
+        .map_err(|error| format!("{}: {}", output.display(), error))?;
+    match json {
+        true => print_json(&json!({ "ok": true, "file": output })),
+        false => println!("{}", output.display()),
+    }
+    Ok(())
+}
+
+pub fn cfg(
+    source: &SourceArgs,
+    mermaid: bool,
+    maps: &[String],
+    instructions: Option<&Path>,
+    output: Option<&Path>,
+    json: bool,
+) -> Result<(), String> {
+    let set = load_instructions(instructions)?;
+    let program = load_program(source, json)?;
+    let map = load_map(&program, maps, &set)?;
+    let flow = ControlFlow::new(&program, &map);
+    let content = match (json, mermaid) {
+        (true, _) => serde_json::to_string_pretty(&json!({ "ok": true, "cfg": flow })).unwrap(),
+        (false, true) => flow.to_mermaid(),
+        (false, false) => flow.to_dot(),
+    };
+    match output {
+        Some(output) => std::fs::write(output, content)
+            .map_err(|error| format!("{}: {}", output.display(), error)),
+        None => {
+            print!("{}", content);
+            Ok(())
+        }
+    }
+}
+
+pub fn check(
+    source: &SourceArgs,
+    entries: &[String],
+    maps: &[String],
+    instructions: Option<&Path>,
+    json: bool,
+) -> Result<(), String> {
+    let set = load_instructions(instructions)?;
+    let file = source.file.as_path();
+    let text =
+        std::fs::read_to_string(file).map_err(|error| format!("{}: {}", file.display(), error))?;
+    // 地址重叠也作为警告列出，所以不检查image
+    let program = Program::compile(&text, &source.scratch.registers).map_err(|error| {
+        if json {
+            print_json(&json!({ "ok": false, "message": error.to_string(), "error": error }));
+        }
+        format!("{}: {}", file.display(), error)
+    })?;
+    let map = load_map(&program, maps, &set)?;
+    let mut entries = entries
+        .iter()
+        .map(|entry| {
+            program
+                .label(entry)
+                .or_else(|| parse_number(entry).and_then(|x| u16::try_from(x).ok()))
+                .ok_or_else(|| format!("未定义的标号: {}", entry))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    if entries.is_empty() {
+        entries.push(0);
+        entries.extend(program.label("FETCH"));
+    }
+    let warnings = program.analyze(&entries, &map);
+    // 解析各条微指令时发现的警告，提示太多，不列出
+    let diagnostics = program
+        .instructions()
+        .iter()
+        .flat_map(|instruction| {
+            let diagnostics = instruction.encoding.lints.iter();
+            diagnostics.map(move |lint| (instruction, lint.diagnostic(&instruction.source)))
+        })
+        .filter(|(_, diagnostic)| diagnostic.severity <= Severity::Warning)
+        .collect::<Vec<_>>();
+    let count = warnings.len() + diagnostics.len();
+    if json {
+        print_json(&json!({
+            "ok": count == 0,
+            "warnings": warnings
+                .iter()
+                .map(|warning| json!({ "message": warning.kind.to_string(), "warning": warning }))
+                .collect::<Vec<_>>(),
+            "diagnostics": diagnostics
+                .iter()
+                .map(|(instruction, diagnostic)| json!({
+                    "line": instruction.line,
+                    "address": instruction.address,
+                    "diagnostic": diagnostic,
+                }))
+                .collect::<Vec<_>>(),
+        }));
+    } else {
+        let mut lines = warnings
+            .iter()
+            .map(|warning| (warning.line, format!("警告: {}", warning.kind)))
+            .chain(
+                diagnostics
+                    .iter()
+                    .map(|(instruction, diagnostic)| (instruction.line, diagnostic.to_string())),
+            )
+            .collect::<Vec<_>>();
+        lines.sort_by_key(|(line, _)| *line);
+        for (line, message) in lines {
+            println!("{}:{}: {}", file.display(), line, message);
+        }
+    }
+    match count {
+        0 => Ok(()),
+        count => Err(format!("{}: 发现{}个问题", file.display(), count)),
+    }
+}
+
+pub fn rom(
+    source: &SourceArgs,
+    output: Option<&Path>,
+    args: &ChipArgs,
+    json: bool,
+) -> Result<(), String> {
+    let program = load_program(source, json)?;
+    let layout = Layout::active();
+    let chips = args
+        .slicing(&layout)
+        .slice(&program.image().unwrap(), &layout)
+        .map_err(|error| error.to_string())?;
+    let prefix = output.unwrap_or(&source.file).with_extension("");
+    let extension = match args.format {
+        ChipFormat::Bin => "bin",
+        ChipFormat::Ihex => "hex",
+    };
+    let mut files = vec![];
+    for chip in chips.iter() {
+        let path = PathBuf::from(format!("{}{}.{}", prefix.display(), chip.index, extension));
+        let result = match args.format {
+            ChipFormat::Bin => std::fs::write(&path, chip.to_bin()),
+            ChipFormat::Ihex => std::fs::write(&path, chip.to_intel_hex()),
+        };
+        result.map_err(|error| format!("{}: {}", path.display(), error))?;
+        files.push(json!({
+            "file": path,
+            "begin": chip.begin,
+            "width": chip.width,
+        }));
+        if !json {
+            println!(
+                "{}\t{}-{}",
+                path.display(),
+                chip.begin,
+                chip.begin + chip.width - 1
+            );
+        }
+    }
+    if json {
+        print_json(&json!({ "ok": true, "chips": files }));
+    }
+    Ok(())
+}
+
+pub fn export(
+    source: &SourceArgs,
+    format: MemoryFormat,
+    fill: u64,
+    output: Option<&Path>,
+    json: bool,
+) -> Result<(), String> {
+    let program = load_program(source, json)?;
+    let content = format
+        .export(&program.image().unwrap(), &Layout::active(), fill)
+        .map_err(|error| error.to_string())?;
+    let output = output.map_or_else(
+        || source.file.with_extension(format.extension()),
+        Path::to_path_buf,
+    );
+    std::fs::write(&output, content).map_err(|error| format!("{}: {}", output.display(), error))?;
+    match json {
+        true => print_json(&json!({ "ok": true, "file": output })),
+        false => println!("{}", output.display()),
+    }
+    Ok(())
+}
+
+pub fn import(
+    file: &Path,
+    format: Option<ImageFormat>,
+    fill: Option<u64>,
+    output: Option<&Path>,
+    json: bool,
+) -> Result<(), String> {
+    let data = std::fs::read(file).map_err(|error| format!("{}: {}", file.display(), error))?;
+    let format = format.unwrap_or_else(|| {
+        ImageFormat::detect(file.extension().and_then(|ext| ext.to_str()), &data)
+    });
+    let layout = Layout::active();
+    let mut image = format
+        .import(&data, &layout)
+        .map_err(|error| error.to_string())?;
+    if let Some(fill) = fill {
+        image.retain(|_, hex| hex.iter().fold(0u64, |word, byte| word << 8 | *byte as u64) != fill);
+    }
+    let decompiled = Decompiled::new(&image);
+    let source = decompiled.source();
+    if let Some(output) = output {
+        std::fs::write(output, &source)
+            .map_err(|error| format!("{}: {}", output.display(), error))?;
+    }
+    match (json, output) {
+        (true, _) => print_json(&json!({
+            "ok": true,
+            "format": format,
+            "words": decompiled.words(),
+        })),
+        (false, Some(output)) => println!("{}", output.display()),
+        (false, None) => print!("{}", source),
+    }
+    Ok(())
+}
+
+pub fn trace(
+    source: &SourceArgs,
+    start: Option<u16>,
+    steps: usize,
+    ir: u16,
+    json: bool,
+) -> Result<(), String> {
+    let start = start.map(micro_address).transpose()?;
+    let program = load_program(source, json)?;
+    let start = start
+        .or_else(|| program.instructions().first().map(|x| x.address))
+        .unwrap_or(0);
+    let mut datapath = Datapath {
+        ir,
+        ..Datapath::default()
+    };
+    let trace = Sequencer::new(start).trace(
+        &program.image().unwrap(),
+        &mut datapath,
+        &Signals::default(),
+        steps,
+    );
+    if json {
+        print_json(&json!({ "ok": trace.error.is_none(), "trace": trace }));
+    } else {
+        for address in trace.addresses.iter() {
+            let source = program
+                .instructions()
+                .iter()
+                .find(|x| x.address == *address)
+                .map_or("", |x| x.source.as_str());
+            println!("{:03X}: {}", address, source);
+        }
+    }
+    match trace.error {
+        Some(error) => Err(error.to_string()),
+        None => Ok(()),
+    }
+}
+
+/// 指令定义文件中的微程序入口，加上--map指定的映射
+fn load_map(
+    program: &Program,
+    maps: &[String],
+    set: &InstructionSet,
+) -> Result<BTreeMap<u8, u16>, String> {
+    let mut map = set.map(program).map_err(|error| error.to_string())?;
+    for item in maps {
+        let (opcode, target) = item
+            .split_once('=')
+            .ok_or_else(|| format!("映射应为操作码=入口: {}", item))?;
+        let opcode = parse_number(opcode)
+            .and_then(|x| u8::try_from(x).ok())
+            .ok_or_else(|| format!("不合法的操作码: {}", opcode))?;
+        let target = program
+            .label(target.trim())
+            .or_else(|| parse_number(target).and_then(|x| u16::try_from(x).ok()))
+            .ok_or_else(|| format!("未定义的标号: {}", target))?;
+        map.insert(opcode, target);
+    }
+    Ok(map)
+}
+
+/// 按微程序和映射创建整机，memory为主存内容或者.asm文件
+pub fn load_machine(
+    source: &SourceArgs,
+    memory: Option<&Path>,
+    maps: &[String],
+    set: &InstructionSet,
+    json: bool,
+) -> Result<(Program, Machine), String> {
+    let program = load_program(source, json)?;
+    let map = load_map(&program, maps, set)?;
+    let mut machine = Machine::new(program.image().unwrap(), map);
+    if let Some(memory) = memory {
+        let text = std::fs::read_to_string(memory)
+            .map_err(|error| format!("{}: {}", memory.display(), error))?;
+        let blocks = match memory
+            .extension()
+            .is_some_and(|x| x.eq_ignore_ascii_case("asm"))
+        {
+            true => Assembly::assemble_with(&text, set)
+                .map_err(|error| format!("{}: {}", memory.display(), error))?
+                .lines()
+                .iter()
+                .map(|line| (line.address, line.words.clone()))
+                .collect(),
+            false => parse_memory(&text)?,
+        };
+        for (address, words) in blocks {
+            machine.load(address, &words);
+        }
+    }
+    Ok((program, machine))
+}
+
+pub fn run(
+    source: &SourceArgs,
+    memory: Option<&Path>,
+    maps: &[String],
+    instructions: Option<&Path>,
+    options: &RunArgs,
+    json: bool,
+) -> Result<(), String> {
+    let start = micro_address(options.start)?;
+    let set = load_instructions(instructions)?;
+    let (_, mut machine) = load_machine(source, memory, maps, &set, json)?;
+    machine.sequencer.jump(start);
+    machine.set_pc(options.pc);
+    let result = machine.run(options.cycles, |_| false);
+    if json {
+        print_json(&json!({
+            "ok": result.is_ok(),
+            "message": result.as_ref().err().map(|error| error.to_string()),
+            "machine": machine,
+        }));
+    } else {
+        print_machine(&machine);
+    }
+    result.map(|_| ()).map_err(|error| error.to_string())
+}
+
+pub fn assemble(
+    file: &Path,
+    output: Option<&Path>,
+    listing: Option<&Path>,
+    instructions: Option<&Path>,
+    json: bool,
+) -> Result<(), String> {
+    let set = load_instructions(instructions)?;
+    let source =
+        std::fs::read_to_string(file).map_err(|error| format!("{}: {}", file.display(), error))?;
+    let assembly = Assembly::assemble_with(&source, &set).map_err(|error| {
+        if json {
+            print_json(&json!({ "ok": false, "message": error.to_string(), "error": error }));
+        }
+        format!("{}: {}", file.display(), error)
+    })?;
+    if let Some(listing) = listing {
+        std::fs::write(listing, assembly.listing())
+            .map_err(|error| format!("{}: {}", listing.display(), error))?;
+    }
+    let content = match json {
+        true => serde_json::to_string_pretty(&json!({
+            "ok": true,
+            "lines": assembly.lines(),
+        }))
+        .unwrap(),
+        false => assembly
+            .lines()
+            .iter()
+            .filter(|line| !line.words.is_empty())
+            .map(|line| {
+                let words = line.words.iter().map(|word| format!("{:04X}", word));
+                format!(
+                    "{:04X}: {}\n",
+                    line.address,
+                    words.collect::<Vec<_>>().join(" ")
+                )
+            })
+            .collect::<String>(),
+    };
+    match output {
+        Some(output) => std::fs::write(output, content)
+            .map_err(|error| format!("{}: {}", output.display(), error)),
+        None => {
+            print!("{}", content);
+            Ok(())
+        }
+    }
+}
+
+/// 命令行指定的微地址必须在当前微指令格式的控存范围内
+fn micro_address(address: u16) -> Result<u16, String> {
+    let max_address = Layout::active().max_address();
+    match address > max_address {
+        true => Err(format!(
+            "微地址{:X}H超出了控存范围(0-{:X}H)",
+            address, max_address
+        )),
+        false => Ok(address),
+    }
+}
+
+/// 没有指定指令定义文件时使用TEC-2的基本指令
+pub fn load_instructions(path: Option<&Path>) -> Result<InstructionSet, String> {
+    match path {
+        Some(path) => InstructionSet::load(path).map_err(|error| error.to_string()),
+        None => Ok(InstructionSet::tec2()),
+    }
+}
+
+pub fn maprom(
+    instructions: &Path,
+    source: &SourceArgs,
+    output: Option<&Path>,
+    json: bool,
+) -> Result<(), String> {
+    let set = load_instructions(Some(instructions))?;
+    let program = load_program(source, json)?;
+    let map = set.map(&program).map_err(|error| error.to_string())?;
+    let content = match json {
+        true => serde_json::to_string_pretty(&json!({ "ok": true, "map": map })).unwrap(),
+        false => map
+            .iter()
+            .map(|(opcode, address)| format!("{:02X}: {:03X}\n", opcode, address))
+            .collect::<String>(),
+    };
+    match output {
+        Some(output) => std::fs::write(output, content)
+            .map_err(|error| format!("{}: {}", output.display(), error)),
+        None => {
+            print!("{}", content);
+            Ok(())
+        }
+    }
+}
+
+pub fn disassemble(file: &Path, instructions: Option<&Path>, json: bool) -> Result<(), String> {
+    let set = load_instructions(instructions)?;
+    let text =
+        std::fs::read_to_string(file).map_err(|error| format!("{}: {}", file.display(), error))?;
+    // 合并地址连续的行，双字指令可以跨行
+    let mut blocks: Vec<(u16, Vec<u16>)> = vec![];
+    for (address, words) in parse_memory(&text)? {
+        match blocks.last_mut() {
+            Some((start, last)) if start.wrapping_add(last.len() as u16) == address => {
+                last.extend(words)
+            }
+            _ => blocks.push((address, words)),
+        }
+    }
+    let lines = blocks
+        .iter()
+        .flat_map(|(address, words)| set.disassemble(*address, words))
+        .collect::<Vec<_>>();
+    match json {
+        true => print_json(&json!({ "ok": true, "lines": lines })),
+        false => {
+            for line in lines.iter() {
+                let words = line.words.iter().map(|word| format!("{:04X}", word));
+                println!(
+                    "{:04X}  {:<10}  {}",
+                    line.address,
+                    words.collect::<Vec<_>>().join(" "),
+                    line.text
+                );
+            }
+        }
+    }
+    Ok(())
+}
+
+pub fn monitor(
+    source: &SourceArgs,
+    memory: Option<&Path>,
+    maps: &[String],
+    instructions: Option<&Path>,
+    fetch: Option<u16>,
+    pc: u16,
+    cycles: u64,
+) -> Result<(), String> {
+    let fetch = fetch.map(micro_address).transpose()?;
+    let set = load_instructions(instructions)?;
+    let (program, mut machine) = load_machine(source, memory, maps, &set, false)?;
+    let fetch = fetch.or_else(|| program.label("FETCH")).unwrap_or(0);
+    machine.set_pc(pc);
+    monitor::run(Monitor::new(machine, set, fetch, cycles));
+    Ok(())
+}
+
+/// 每行为"地址: 字 字 ..."，没有地址时接着上一行
+fn parse_memory(text: &str) -> Result<Vec<(u16, Vec<u16>)>, String> {
+    let mut result = vec![];
+    let mut address = 0u16;
+    for (index, line) in text.lines().enumerate() {
+        let error = || format!("第{}行: 不合法的主存内容", index + 1);
+        let line = line.split(';').next().unwrap_or("").trim();
+        let words = match line.split_once(':') {
+            Some((start, words)) => {
+                address = u16::from_str_radix(start.trim(), 16).map_err(|_| error())?;
+                words
+            }
+            None => line,
+        };
+        let words = words
+            .split_whitespace()
+            .map(|word| u16::from_str_radix(word, 16).map_err(|_| error()))
+            .collect::<Result<Vec<_>, _>>()?;
+        let length = words.len() as u16;
+        result.push((address, words));
+        address = address.wrapping_add(length);
+    }
+    Ok(result)
+}
+
+fn print_machine(machine: &Machine) {
+    let datapath = &machine.datapath;
+    for (i, value) in datapath.registers.iter().enumerate() {
+        print!(
+            "R{:<2} {:04X}{}",
+            i,
+            value,
+            if i % 4 == 3 { "\n" } else { "  " }
+        );
+    }
+    println!(
+        "Q   {:04X}  AR  {:04X}  IR  {:04X}  PC  {:04X}",
+        datapath.q, machine.ar, datapath.ir, datapath.registers[PC]
+    );
+    let flags = &datapath.flags;
+    println!(
+        "C={} Z={} V={} S={}",
+        flags.c as u8, flags.z as u8, flags.v as u8, flags.s as u8
+    );
+    println!(
+        "微地址 {:03X}H  已执行{}条微指令",
+        machine.sequencer.address, machine.cycles
+    );
+    for (port, value) in machine.output.iter() {
+        println!("输出 {:04X}: {:04X}", port, value);
+    }
+}
+
+pub fn explain(expr: &str, json: bool) -> Result<(), String> {
+    let result = ParseResult::parse(expr);
+    if json {
+        print_json(&result.report(expr));
+    }
+    match &result {
+        ParseResult::Result(encoding) => {
+            if !json {
+                println!("表达式\t{}", expr);
+                println!("十六进制\t{}", hex::encode_upper(&encoding.hex));
+                print_fields(encoding, true);
+                for diagnostic in result.diagnostics(expr) {
+                    println!("{}", diagnostic);
+                }
+            }
+            Ok(())
+        }
+        error => Err(error.to_string()),
+    }
+}
+
+fn print_fields(encoding: &Encoding, state: bool) {
+    println!("字段\t位\t值\t含义{}", if state { "\t状态" } else { "" });
+    for field in encoding.fields.iter() {
+        print!(
+            "{}\t{}-{}\t{}\t{}",
+            field.name,
+            field.begin,
+            field.begin as usize + field.width - 1,
+            field.bits,
+            field.meaning
+        );
+        match state {
+            true => println!(
+                "\t{}",
+                match field.state {
+                    FieldState::Set => "指定",
+                    FieldState::Default => "默认",
+                    FieldState::DontCare => "任意",
+                }
+            ),
+            false => println!(),
+        }
+    }
+    let decoder = Decoder::new(&encoding.hex);
+    if decoder
+        .layout()
+        .position(&Instrument::MIO([0; 1]))
+        .is_some()
+    {
+        println!("存储器\t\t\t{}", decoder.mem());
+    }
+    if decoder.instrument(&Instrument::CI([0; 4])).value() != Ci::SEQ as u16
+        && let Some(condition) = decoder.condition()
+    {
+        println!("条件\t\t\tCC#={}", condition);
+    }
+}
@@ -0,0 +1,623 @@
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use std::path::PathBuf;
+use std::process::ExitCode;
+use tec2_parser::ast::token::Identifier;
+use tec2_parser::grammar;
+use tec2_parser::parser::format::{OutputMode, Padding, WordFormat, WordOrder};
+use tec2_parser::parser::layout::Layout;
+use tec2_parser::program::{ChipSlicing, ImageFormat, ListingFormat, MemoryFormat, parse_number};
+
+mod command;
+mod monitor;
+mod repl;
+
+pub use monitor::Monitor;
+
+/// 不带子命令时启动终端界面，标准输入不是终端时逐行解析
+#[derive(Parser)]
+#[command(name = "tec2tool", version, about = "TEC-2 微指令工具")]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+    /// 从标准输入逐行读取表达式
+    #[arg(long)]
+    pub repl: bool,
+    /// 以JSON格式输出结果和错误
+    #[arg(long, global = true)]
+    pub json: bool,
+    /// 微指令格式文件(.toml或.json)，默认为TEC-2
+    #[arg(long, global = true)]
+    pub layout: Option<PathBuf>,
+    #[command(flatten)]
+    pub format: FormatArgs,
+    #[command(flatten)]
+    pub scratch: ScratchArgs,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// 把表达式编码为微指令
+    Encode {
+        expr: String,
+        #[command(flatten)]
+        format: FormatArgs,
+        #[command(flatten)]
+        scratch: ScratchArgs,
+    },
+    /// 把十六进制微指令解码为各个字段
+    Decode {
+        /// 14位十六进制，或者带空格的四个16位字
+        #[arg(required = true, num_args = 1..)]
+        hex: Vec<String>,
+        #[command(flatten)]
+        words: WordArgs,
+    },
+    /// 编译微程序文件
+    Build {
+        #[command(flatten)]
+        source: SourceArgs,
+        /// 输出文件，默认输出到标准输出
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+        #[command(flatten)]
+        format: FormatArgs,
+    },
+    /// 生成微程序的列表文件，包括地址、编码、二进制、源代码和字段说明
+    Listing {
+        #[command(flatten)]
+        source: SourceArgs,
+        /// 输出Markdown表格
+        #[arg(long)]
+        markdown: bool,
+        /// 输出文件，默认输出到标准输出
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// 检查无法到达的微指令、转移到空地址、地址重叠、顺序执行到空地址和相邻微指令的配合，有问题时返回非0
+    Check {
+        #[command(flatten)]
+        source: SourceArgs,
+        /// 入口地址或标号，可以多次指定，默认为0和标号FETCH
+        #[arg(long = "entry")]
+        entries: Vec<String>,
+        /// 操作码(IR15-8)对应的微程序入口，可以多次指定
+        #[arg(long = "map", value_name = "OPCODE=TARGET")]
+        maps: Vec<String>,
+        /// 指令定义文件，其中的微程序入口也视为入口
+        #[arg(long)]
+        instructions: Option<PathBuf>,
+    },
+    /// 输出微程序的控制流图，默认为Graphviz的DOT格式
+    Cfg {
+        #[command(flatten)]
+        source: SourceArgs,
+        /// 输出Mermaid的flowchart
+        #[arg(long)]
+        mermaid: bool,
+        /// 操作码(IR15-8)对应的微程序入口，用于画出JMAP的分支，可以多次指定
+        #[arg(long = "map", value_name = "OPCODE=TARGET")]
+        maps: Vec<String>,
+        /// 指令定义文件，按其中的微程序入口画出JMAP的分支
+        #[arg(long)]
+        instructions: Option<PathBuf>,
+        /// 输出文件，默认输出到标准输出
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// 把微程序导出为每个字段一列的CSV，可以用import还原
+    Csv {
+        #[command(flatten)]
+        source: SourceArgs,
+        /// 输出文件，默认为微程序文件名加上.csv
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// 列出表达式生成的各个字段及含义
+    Explain { expr: String },
+    /// 在微程序控制器上依次执行微程序，输出经过的地址
+    Trace {
+        #[command(flatten)]
+        source: SourceArgs,
+        /// 起始地址，默认为第一条微指令的地址
+        #[arg(long, value_parser = parse_address)]
+        start: Option<u16>,
+        /// 最多执行的微指令条数
+        #[arg(long, default_value_t = 64)]
+        steps: usize,
+        /// 指令寄存器的值，用于条件IR10-8和A口/B口选择
+        #[arg(long, value_parser = parse_address, default_value = "0")]
+        ir: u16,
+    },
+    /// 用微程序作为控存运行主存中的程序，结束后输出寄存器
+    Run {
+        #[command(flatten)]
+        source: SourceArgs,
+        /// 主存内容，每行为"地址: 字 字 ..."(十六进制)，没有地址时接着上一行；
+        /// 扩展名为.asm时作为汇编程序汇编后装入
+        #[arg(long)]
+        memory: Option<PathBuf>,
+        /// 操作码(IR15-8)对应的微程序入口，如20H=ADD，可以多次指定
+        #[arg(long = "map", value_name = "OPCODE=TARGET")]
+        maps: Vec<String>,
+        #[command(flatten)]
+        options: RunArgs,
+        /// 指令定义文件，按其中的微程序入口生成操作码的映射，--map优先
+        #[arg(long)]
+        instructions: Option<PathBuf>,
+    },
+    /// 汇编TEC-2机器指令程序，输出"地址: 字 字 ..."格式的主存内容
+    Asm {
+        file: PathBuf,
+        /// 输出文件，默认输出到标准输出
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+        /// 同时输出地址、机器码和源代码对照的列表文件
+        #[arg(long)]
+        listing: Option<PathBuf>,
+        /// 指令定义文件(.toml或.json)，默认为TEC-2的基本指令
+        #[arg(long)]
+        instructions: Option<PathBuf>,
+    },
+    /// 把主存内容反汇编为TEC-2机器指令
+    Disasm {
+        /// 每行为"地址: 字 字 ..."(十六进制)，没有地址时接着上一行
+        file: PathBuf,
+        /// 指令定义文件(.toml或.json)，默认为TEC-2的基本指令
+        #[arg(long)]
+        instructions: Option<PathBuf>,
+    },
+    /// 用微程序作为控存，模拟TEC-2监控程序的A、U、G、D、E、R、P、T命令
+    Monitor {
+        #[command(flatten)]
+        source: SourceArgs,
+        /// 预先装入的主存内容或者.asm文件
+        #[arg(long)]
+        memory: Option<PathBuf>,
+        /// 操作码(IR15-8)对应的微程序入口，如20H=ADD，可以多次指定
+        #[arg(long = "map", value_name = "OPCODE=TARGET")]
+        maps: Vec<String>,
+        /// 指令定义文件，按其中的微程序入口生成操作码的映射，--map优先
+        #[arg(long)]
+        instructions: Option<PathBuf>,
+        /// 取指微程序的地址，默认为标号FETCH，没有时为0
+        #[arg(long, value_parser = parse_address)]
+        fetch: Option<u16>,
+        /// 程序的起始地址(PC)
+        #[arg(long, value_parser = parse_address, default_value = "0")]
+        pc: u16,
+        /// 一条命令最多执行的微指令条数
+        #[arg(long, default_value_t = 1000000)]
+        cycles: u64,
+    },
+    /// 把控存按EPROM芯片拆分，每片输出一个二进制或Intel HEX文件
+    Rom {
+        #[command(flatten)]
+        source: SourceArgs,
+        /// 输出文件名的前缀，第i片为"前缀i.bin"或"前缀i.hex"，默认为微程序的文件名
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+        #[command(flatten)]
+        chips: ChipArgs,
+    },
+    /// 导出控存的存储器初始化文件，用于Verilog、Vivado、Quartus或Logisim
+    Export {
+        #[command(flatten)]
+        source: SourceArgs,
+        #[arg(long, value_enum)]
+        format: MemoryKind,
+        /// 没有微指令的地址填充的值
+        #[arg(long, value_parser = parse_word, default_value = "0")]
+        fill: u64,
+        /// 输出文件，默认为微程序文件名加上对应的扩展名
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// 读取已有的控存映像，还原为带地址和标号注释的微程序
+    Import {
+        /// 二进制、Intel HEX、.mif、CSV、tec-2.py生成的Markdown表格或每行一条十六进制微指令的文本
+        file: PathBuf,
+        /// 映像的格式，默认按扩展名和内容判断
+        #[arg(long, value_enum, default_value_t = ImageKind::Auto)]
+        format: ImageKind,
+        /// 等于该值的地址视为未使用，不输出
+        #[arg(long, value_parser = parse_word, default_value = "0")]
+        fill: u64,
+        /// 输出全部地址，包括等于填充值的地址
+        #[arg(long)]
+        keep_fill: bool,
+        /// 输出文件，默认输出到标准输出
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// 按指令定义文件和微程序生成映射ROM，每行为"操作码: 微程序入口"
+    Maprom {
+        /// 指令定义文件(.toml或.json)
+        instructions: PathBuf,
+        #[command(flatten)]
+        source: SourceArgs,
+        /// 输出文件，默认输出到标准输出
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+}
+
+/// 暂存寄存器只能是Q或通用寄存器
+fn parse_scratch(text: &str) -> Result<Identifier, String> {
+    match grammar::IdentifierParser::new().parse(text.trim()) {
+        Ok(identifier @ (Identifier::Q | Identifier::R(_))) => Ok(identifier),
+        _ => Err(format!("只能用Q或R0到R11作为暂存寄存器: {}", text)),
+    }
+}
+
+fn parse_address(text: &str) -> Result<u16, String> {
+    parse_number(text)
+        .and_then(|value| u16::try_from(value).ok())
+        .ok_or_else(|| format!("不合法的数字: {}", text))
+}
+
+/// 微指令可能超过32位，支持0x前缀和H后缀的十六进制
+fn parse_word(text: &str) -> Result<u64, String> {
+    let text = text.trim();
+    let result = match text
+        .strip_prefix("0x")
+        .or(text.strip_prefix("0X"))
+        .or(text.strip_suffix(['H', 'h']))
+    {
+        Some(hex) => u64::from_str_radix(hex, 16),
+        None => text.parse(),
+    };
+    result.map_err(|_| format!("不合法的数字: {}", text))
+}
+
+/// 编译微程序时一个周期完成不了的表达式的拆分方式
+#[derive(Args)]
+pub struct ScratchArgs {
+    /// 一个周期完成不了时拆成多条微指令，用来存放中间结果的Q或通用寄存器，可以多次指定，默认为Q
+    #[arg(long = "scratch", value_name = "SCRATCH", value_parser = parse_scratch)]
+    registers: Vec<Identifier>,
+}
+
+/// 需要编译的微程序文件
+#[derive(Args)]
+pub struct SourceArgs {
+    /// 微程序文件
+    file: PathBuf,
+    #[command(flatten)]
+    scratch: ScratchArgs,
+}
+
+#[derive(Args)]
+pub struct RunArgs {
+    /// 微程序的起始地址，默认为0
+    #[arg(long, value_parser = parse_address, default_value = "0")]
+    start: u16,
+    /// 程序的起始地址(PC)
+    #[arg(long, value_parser = parse_address, default_value = "0")]
+    pc: u16,
+    /// 最多执行的微指令条数
+    #[arg(long, default_value_t = 10000)]
+    cycles: u64,
+}
+
+#[derive(Args)]
+pub struct ChipArgs {
+    /// 每片芯片的数据位数
+    #[arg(long, default_value_t = 8)]
+    chip_width: u8,
+    /// 芯片的顺序，high-first时第0片为微指令的最高位
+    #[arg(long, value_enum, default_value_t = OrderKind::HighFirst)]
+    order: OrderKind,
+    /// 片内数据位反序
+    #[arg(long)]
+    reverse: bool,
+    /// 起始地址，默认为0
+    #[arg(long, value_parser = parse_address, default_value = "0")]
+    start: u16,
+    /// 结束地址(包含)，默认为微地址的最大值
+    #[arg(long, value_parser = parse_address)]
+    end: Option<u16>,
+    /// 输出文件的格式
+    #[arg(long, value_enum, default_value_t = ChipFormat::Bin)]
+    format: ChipFormat,
+    /// 没有微指令的地址填充的值
+    #[arg(long, value_parser = parse_word, default_value = "0")]
+    fill: u64,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum MemoryKind {
+    Readmemh,
+    Readmemb,
+    Coe,
+    Mif,
+    Logisim,
+}
+
+impl From<MemoryKind> for MemoryFormat {
+    fn from(kind: MemoryKind) -> Self {
+        match kind {
+            MemoryKind::Readmemh => MemoryFormat::ReadMemH,
+            MemoryKind::Readmemb => MemoryFormat::ReadMemB,
+            MemoryKind::Coe => MemoryFormat::Coe,
+            MemoryKind::Mif => MemoryFormat::Mif,
+            MemoryKind::Logisim => MemoryFormat::Logisim,
+        }
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum ImageKind {
+    Auto,
+    Bin,
+    Ihex,
+    Mif,
+    Text,
+    Csv,
+    Markdown,
+}
+
+impl ImageKind {
+    fn image_format(&self) -> Option<ImageFormat> {
+        match self {
+            ImageKind::Auto => None,
+            ImageKind::Bin => Some(ImageFormat::Binary),
+            ImageKind::Ihex => Some(ImageFormat::IntelHex),
+            ImageKind::Mif => Some(ImageFormat::Mif),
+            ImageKind::Text => Some(ImageFormat::Text),
+            ImageKind::Csv => Some(ImageFormat::Csv),
+            ImageKind::Markdown => Some(ImageFormat::Markdown),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum ChipFormat {
+    Bin,
+    Ihex,
+}
+
+#[derive(Args)]
+pub struct FormatArgs {
+    /// 输出格式
+    #[arg(long, value_enum, default_value_t = FormatKind::Hex)]
+    format: FormatKind,
+    /// 补齐到16位的整数倍时0的位置，仅在words格式下有效
+    #[arg(long, value_enum, default_value_t = PaddingKind::Left)]
+    padding: PaddingKind,
+    /// 16位字的顺序，仅在words格式下有效
+    #[arg(long, value_enum, default_value_t = OrderKind::HighFirst)]
+    order: OrderKind,
+}
+
+/// 输入为补齐到16位整数倍的字时的排列方式
+#[derive(Args)]
+pub struct WordArgs {
+    /// 补的0在高位还是低位
+    #[arg(long, value_enum, default_value_t = PaddingKind::Left)]
+    padding: PaddingKind,
+    /// 16位字的顺序
+    #[arg(long, value_enum, default_value_t = OrderKind::HighFirst)]
+    order: OrderKind,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum FormatKind {
+    Hex,
+    Words,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum PaddingKind {
+    Left,
+    Right,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum OrderKind {
+    HighFirst,
+    LowFirst,
+}
+
+impl ChipArgs {
+    fn slicing(&self, layout: &Layout) -> ChipSlicing {
+        ChipSlicing {
+            width: self.chip_width,
+            order: match self.order {
+                OrderKind::HighFirst => WordOrder::HighFirst,
+                OrderKind::LowFirst => WordOrder::LowFirst,
+            },
+            reverse: self.reverse,
+            range: self.start..=self.end.unwrap_or(layout.max_address()),
+            fill: self.fill,
+        }
+    }
+}
+
+impl FormatArgs {
+    fn output_mode(&self) -> OutputMode {
+        match self.format {
+            FormatKind::Hex => OutputMode::Hex,
+            FormatKind::Words => OutputMode::Words(word_format(self.padding, self.order)),
+        }
+    }
+}
+
+impl WordArgs {
+    fn word_format(&self) -> WordFormat {
+        word_format(self.padding, self.order)
+    }
+}
+
+fn word_format(padding: PaddingKind, order: OrderKind) -> WordFormat {
+    WordFormat::new(
+        match padding {
+            PaddingKind::Left => Padding::Left,
+            PaddingKind::Right => Padding::Right,
+        },
+        match order {
+            OrderKind::HighFirst => WordOrder::HighFirst,
+            OrderKind::LowFirst => WordOrder::LowFirst,
+        },
+    )
+}
+
+impl Cli {
+    /// 加载--layout指定的微指令格式
+    pub fn load_layout(&self) -> Result<(), String> {
+        match &self.layout {
+            Some(path) => Layout::load(path)
+                .and_then(Layout::set_active)
+                .map_err(|error| error.to_string()),
+            None => Ok(()),
+        }
+    }
+
+    pub fn run_repl(&self) -> ExitCode {
+        repl::run(
+            &self.format.output_mode(),
+            &self.scratch.registers,
+            self.json,
+        )
+    }
+}
+
+impl Command {
+    pub fn run(self, json: bool) -> ExitCode {
+        let result = match self {
+            Command::Encode {
+                expr,
+                format,
+                scratch,
+            } => command::encode(&expr, &format.output_mode(), &scratch.registers, json),
+            Command::Decode { hex, words } => {
+                command::decode(&hex.join(" "), &words.word_format(), json)
+            }
+            Command::Build {
+                source,
+                output,
+                format,
+            } => command::build(&source, output.as_deref(), &format.output_mode(), json),
+            Command::Listing {
+                source,
+                markdown,
+                output,
+            } => {
+                let format = match markdown {
+                    true => ListingFormat::Markdown,
+                    false => ListingFormat::Text,
+                };
+                command::listing(&source, format, output.as_deref(), json)
+            }
+            Command::Check {
+                source,
+                entries,
+                maps,
+                instructions,
+            } => command::check(&source, &entries, &maps, instructions.as_deref(), json),
+            Command::Cfg {
+                source,
+                mermaid,
+                maps,
+                instructions,
+                output,
+            } => command::cfg(
+                &source,
+                mermaid,
+                &maps,
+                instructions.as_deref(),
+                output.as_deref(),
+                json,
+            ),
+            Command::Csv { source, output } => command::csv(&source, output.as_deref(), json),
+            Command::Explain { expr } => command::explain(&expr, json),
+            Command::Trace {
+                source,
+                start,
+                steps,
+                ir,
+            } => command::trace(&source, start, steps, ir, json),
+            Command::Run {
+                source,
+                memory,
+                maps,
+                options,
+                instructions,
+            } => command::run(
+                &source,
+                memory.as_deref(),
+                &maps,
+                instructions.as_deref(),
+                &options,
+                json,
+            ),
+            Command::Asm {
+                file,
+                output,
+                listing,
+                instructions,
+            } => command::assemble(
+                &file,
+                output.as_deref(),
+                listing.as_deref(),
+                instructions.as_deref(),
+                json,
+            ),
+            Command::Disasm { file, instructions } => {
+                command::disassemble(&file, instructions.as_deref(), json)
+            }
+            Command::Monitor {
+                source,
+                memory,
+                maps,
+                instructions,
+                fetch,
+                pc,
+                cycles,
+            } => command::monitor(
+                &source,
+                memory.as_deref(),
+                &maps,
+                instructions.as_deref(),
+                fetch,
+                pc,
+                cycles,
+            ),
+            Command::Rom {
+                source,
+                output,
+                chips,
+            } => command::rom(&source, output.as_deref(), &chips, json),
+            Command::Export {
+                source,
+                format,
+                fill,
+                output,
+            } => command::export(&source, format.into(), fill, output.as_deref(), json),
+            Command::Import {
+                file,
+                format,
+                fill,
+                keep_fill,
+                output,
+            } => command::import(
+                &file,
+                format.image_format(),
+                (!keep_fill).then_some(fill),
+                output.as_deref(),
+                json,
+            ),
+            Command::Maprom {
+                instructions,
+                source,
+                output,
+            } => command::maprom(&instructions, &source, output.as_deref(), json),
+        };
+        match result {
+            Ok(()) => ExitCode::SUCCESS,
+            Err(message) => {
+                eprintln!("{}", message);
+                ExitCode::FAILURE
+            }
+        }
+    }
+}
@@ -0,0 +1,356 @@
+use std::io::{BufRead, IsTerminal, Write};
+use tec2_parser::asm::{Assembly, Format, InstructionSet};
+use tec2_parser::sim::{IP, MEMORY_SIZE, Machine, PC};
+
+/// SP所在的通用寄存器
+const SP: usize = 4;
+
+/// U命令一次反汇编的指令数
+const UNASSEMBLE_LINES: usize = 16;
+
+/// D命令一次显示的行数，每行8个字
+const DUMP_LINES: usize = 8;
+
+const HELP: &str = "\
+A [地址]          从地址开始逐行汇编，空行结束
+U [地址]          反汇编
+G [地址]          从地址开始运行，到最外层的RET为止
+D [地址]          按字显示主存
+E 地址 [字 ...]   修改主存，不带字时逐个修改，空行结束
+R [寄存器 值]     显示或修改寄存器
+T [条数]          单步执行，进入子程序
+P                 单步执行，不进入子程序
+Q                 退出";
+
+enum Mode {
+    Command,
+    Assemble(u16),
+    Edit(u16),
+}
+
+/// 模拟TEC-2的监控程序，地址和数据都是十六进制
+pub struct Monitor {
+    machine: Machine,
+    set: InstructionSet,
+    fetch: u16,      // 取指微程序的入口，回到这里时一条机器指令执行完毕
+    max_cycles: u64, // 一条命令最多执行的微指令条数
+    mode: Mode,
+    assemble: u16, // 下一次A、U、D命令默认的地址
+    unassemble: u16,
+    dump: u16,
+}
+
+impl Monitor {
+    pub fn new(mut machine: Machine, set: InstructionSet, fetch: u16, max_cycles: u64) -> Self {
+        machine.sequencer.jump(fetch);
+        let pc = machine.pc();
+        Self {
+            machine,
+            set,
+            fetch,
+            max_cycles,
+            mode: Mode::Command,
+            assemble: pc,
+            unassemble: pc,
+            dump: pc,
+        }
+    }
+
+    pub fn machine(&self) -> &Machine {
+        &self.machine
+    }
+
+    pub fn prompt(&self) -> String {
+        match self.mode {
+            Mode::Command => "> ".to_string(),
+            Mode::Assemble(address) => format!("{:04X}: ", address),
+            Mode::Edit(address) => {
+                format!(
+                    "{:04X} {:04X}: ",
+                    address, self.machine.memory[address as usize]
+                )
+            }
+        }
+    }
+
+    /// 执行一行输入，返回要输出的内容
+    pub fn execute(&mut self, line: &str) -> Result<String, String> {
+        let line = line.trim();
+        match self.mode {
+            Mode::Assemble(address) if line.is_empty() => {
+                self.assemble = address;
+                self.mode = Mode::Command;
+                Ok(String::new())
+            }
+            Mode::Assemble(address) => {
+                let assembly =
+                    Assembly::assemble_with(&format!("ORG {:X}H\n{}", address, line), &self.set)
+                        .map_err(|error| error.kind.to_string())?;
+                let mut next = address;
+                for (address, word) in assembly.image() {
+                    self.machine.memory[address as usize] = word;
+                    next = address.wrapping_add(1);
+                }
+                self.mode = Mode::Assemble(next);
+                Ok(String::new())
+            }
+            Mode::Edit(_) if line.is_empty() => {
+                self.mode = Mode::Command;
+                Ok(String::new())
+            }
+            Mode::Edit(address) => {
+                self.machine.memory[address as usize] = hex(line)?;
+                self.mode = Mode::Edit(address.wrapping_add(1));
+                Ok(String::new())
+            }
+            Mode::Command if line.is_empty() => Ok(String::new()),
+            Mode::Command => self.command(line),
+        }
+    }
+
+    fn command(&mut self, line: &str) -> Result<String, String> {
+        let mut chars = line.chars();
+        let command = chars.next().unwrap().to_string();
+        let rest = chars.as_str();
+        let args = rest
+            .split(|c: char| c.is_whitespace() || c == ',')
+            .filter(|x| !x.is_empty())
+            .collect::<Vec<_>>();
+        let address = |default: u16| args.first().map_or(Ok(default), |x| hex(x));
+        match command.to_ascii_uppercase().as_str() {
+            "A" => {
+                self.mode = Mode::Assemble(address(self.assemble)?);
+                Ok(String::new())
+            }
+            "U" => {
+                let mut address = address(self.unassemble)?;
+                let mut output = String::new();
+                for _ in 0..UNASSEMBLE_LINES {
+                    let line = self.disassemble(address);
+                    address = address.wrapping_add(line.1);
+                    output.push_str(&line.0);
+                    output.push('\n');
+                }
+                self.unassemble = address;
+                Ok(output)
+            }
+            "D" => {
+                let address = address(self.dump)?;
+                let output = (0..DUMP_LINES as u16)
+                    .map(|line| self.dump_line(address.wrapping_add(line * 8)))
+                    .collect();
+                self.dump = address.wrapping_add(DUMP_LINES as u16 * 8);
+                Ok(output)
+            }
+            "E" => {
+                let address = hex(args.first().ok_or("E命令需要地址")?)?;
+                if args.len() == 1 {
+                    self.mode = Mode::Edit(address);
+                }
+                for (i, word) in args[1..].iter().enumerate() {
+                    self.machine.memory[address.wrapping_add(i as u16) as usize] = hex(word)?;
+                }
+                Ok(String::new())
+            }
+            "R" => {
+                if let [register, value] = args[..] {
+                    let index = register_index(register)?;
+                    self.machine.datapath.registers[index] = hex(value)?;
+                } else if !args.is_empty() {
+                    return Err("R命令的格式为: R 寄存器 值".to_string());
+                }
+                Ok(self.registers())
+            }
+            "T" => {
+                let count = args.first().map_or(Ok(1), |x| hex(x))?;
+                let mut output = String::new();
+                for _ in 0..count {
+                    self.instruction()?;
+                    output.push_str(&self.registers());
+                }
+                Ok(output)
+            }
+            "P" => {
+                let pc = self.machine.pc();
+                let def = self
+                    .set
+                    .find_opcode((self.machine.memory[pc as usize] >> 8) as u8);
+                match def.map(|def| (def.mnemonic.as_str(), def.format)) {
+                    Some(("CALA", Format::Address)) => {
+                        let sp = self.machine.datapath.registers[SP];
+                        let next = pc.wrapping_add(2);
+                        self.until(|machine| {
+                            machine.pc() == next && machine.datapath.registers[SP] == sp
+                        })?;
+                    }
+                    _ => self.instruction()?,
+                }
+                Ok(self.registers())
+            }
+            "G" => {
+                if !args.is_empty() {
+                    self.machine.set_pc(address(0)?);
+                }
+                // 程序以RET返回监控程序
+                let ret = self.set.find("RET").map(|def| def.opcode);
+                let sp = self.machine.datapath.registers[SP];
+                self.until(|machine| {
+                    ret == Some((machine.memory[machine.pc() as usize] >> 8) as u8)
+                        && machine.datapath.registers[SP] == sp
+                })?;
+                Ok(self.registers())
+            }
+            "?" | "H" => Ok(format!("{}\n", HELP)),
+            _ => Err(format!("未知的命令: {}", command)),
+        }
+    }
+
+    /// 执行一条机器指令，即执行到下一次回到取指微程序
+    fn instruction(&mut self) -> Result<(), String> {
+        let fetch = self.fetch;
+        self.machine.cycle().map_err(|error| error.to_string())?;
+        let cycles = self
+            .machine
+            .run(self.max_cycles, |machine| {
+                machine.sequencer.address == fetch
+            })
+            .map_err(|error| error.to_string())?;
+        match cycles == self.max_cycles {
+            true => Err(format!("执行了{}条微指令仍未回到取指", cycles)),
+            false => Ok(()),
+        }
+    }
+
+    /// 逐条执行机器指令，直到stop返回true，执行之前已经满足时不执行
+    fn until(&mut self, stop: impl Fn(&Machine) -> bool) -> Result<(), String> {
+        let start = self.machine.cycles;
+        loop {
+            if stop(&self.machine) {
+                return Ok(());
+            }
+            if self.machine.cycles - start >= self.max_cycles {
+                return Err(format!(
+                    "执行了{}条微指令仍未结束",
+                    self.machine.cycles - start
+                ));
+            }
+            self.instruction()?;
+        }
+    }
+
+    /// 返回一行反汇编和指令的字数
+    fn disassemble(&self, address: u16) -> (String, u16) {
+        let memory = &self.machine.memory;
+        let words = [
+            memory[address as usize],
+            memory[(address as usize + 1) % MEMORY_SIZE],
+        ];
+        let Some(line) = self.set.decode(address, &words) else {
+            unreachable!("words不为空");
+        };
+        let codes = line
+            .words
+            .iter()
+            .map(|word| format!("{:04X}", word))
+            .collect::<Vec<_>>()
+            .join(" ");
+        (
+            format!("{:04X}  {:<10}  {}", address, codes, line.text),
+            line.words.len() as u16,
+        )
+    }
+
+    fn dump_line(&self, address: u16) -> String {
+        let words = (0..8)
+            .map(|i| self.machine.memory[address.wrapping_add(i) as usize])
+            .collect::<Vec<_>>();
+        let text = words
+            .iter()
+            .flat_map(|word| word.to_be_bytes())
+            .map(|byte| match byte {
+                0x20..=0x7E => byte as char,
+                _ => '.',
+            })
+            .collect::<String>();
+        let words = words
+            .iter()
+            .map(|word| format!("{:04X}", word))
+            .collect::<Vec<_>>()
+            .join(" ");
+        format!("{:04X}  {}  {}\n", address, words, text)
+    }
+
+    /// 寄存器、标志位和下一条指令
+    fn registers(&self) -> String {
+        let datapath = &self.machine.datapath;
+        let mut output = String::new();
+        for (i, value) in datapath.registers.iter().enumerate() {
+            let name = match i {
+                SP => "SP".to_string(),
+                PC => "PC".to_string(),
+                IP => "IP".to_string(),
+                _ => format!("R{}", i),
+            };
+            output.push_str(&format!(
+                "{}={:04X}{}",
+                name,
+                value,
+                if i % 8 == 7 { "\n" } else { " " }
+            ));
+        }
+        let flags = &datapath.flags;
+        output.push_str(&format!(
+            "C={} Z={} V={} S={}\n",
+            flags.c as u8, flags.z as u8, flags.v as u8, flags.s as u8
+        ));
+        output.push_str(&self.disassemble(self.machine.pc()).0);
+        output.push('\n');
+        output
+    }
+}
+
+fn hex(text: &str) -> Result<u16, String> {
+    let digits = text.strip_suffix(['H', 'h']).unwrap_or(text);
+    u16::from_str_radix(digits, 16).map_err(|_| format!("不合法的十六进制数: {}", text))
+}
+
+/// R0-R15，以及SP、PC、IP
+fn register_index(name: &str) -> Result<usize, String> {
+    let name = name.to_ascii_uppercase();
+    let index = match name.as_str() {
+        "SP" => Some(SP),
+        "PC" => Some(PC),
+        "IP" => Some(IP),
+        _ => name.strip_prefix('R').and_then(|x| x.parse::<usize>().ok()),
+    };
+    index
+        .filter(|x| *x < 16)
+        .ok_or_else(|| format!("未知的寄存器: {}", name))
+}
+
+/// 逐行读取监控命令，Q或输入结束时退出
+pub fn run(mut monitor: Monitor) {
+    let stdin = std::io::stdin();
+    let interactive = stdin.is_terminal();
+    let mut stdout = std::io::stdout();
+    let mut lines = stdin.lock().lines();
+    if interactive {
+        println!("TEC-2 监控程序，输入?查看命令");
+    }
+    loop {
+        if interactive {
+            print!("{}", monitor.prompt());
+            stdout.flush().ok();
+        }
+        let Some(Ok(line)) = lines.next() else {
+            break;
+        };
+        if matches!(monitor.mode, Mode::Command) && line.trim().eq_ignore_ascii_case("Q") {
+            break;
+        }
+        match monitor.execute(&line) {
+            Ok(output) => print!("{}", output),
+            Err(message) => println!("错误: {}", message),
+        }
+    }
+}
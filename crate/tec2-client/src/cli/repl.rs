@@ -0,0 +1,63 @@
+use crate::cli::command::{print_steps, steps_report};
+use std::io::{BufRead, IsTerminal, Write};
+use std::process::ExitCode;
+use tec2_parser::ast::token::Identifier;
+use tec2_parser::compile::compile;
+use tec2_parser::parser::format::OutputMode;
+use tec2_parser::result::ParseResult;
+
+const PROMPT: &str = ">>> ";
+
+/// 每行一个表达式，输出十六进制或者错误信息，有任意一行出错时返回非0
+///
+/// 一条微指令完成不了时和encode一样按scratch拆分，每条微指令输出一行；使用JSON时每行输出一个JSON对象
+pub fn run(output_mode: &OutputMode, scratch: &[Identifier], json: bool) -> ExitCode {
+    let stdin = std::io::stdin();
+    let interactive = stdin.is_terminal();
+    let mut stdout = std::io::stdout();
+    let mut failed = false;
+    let mut lines = stdin.lock().lines();
+    loop {
+        if interactive {
+            print!("{}", PROMPT);
+            stdout.flush().ok();
+        }
+        let Some(Ok(line)) = lines.next() else {
+            break;
+        };
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        let mut result = ParseResult::parse(line);
+        if !result.is_ok() {
+            match compile(line, scratch) {
+                Ok(steps) => {
+                    match json {
+                        true => println!("{}", steps_report(line, &steps)),
+                        false => print_steps(line, &steps, output_mode, false),
+                    }
+                    continue;
+                }
+                Err(error @ ParseResult::ExprParseError { .. }) => result = error,
+                Err(_) => {}
+            }
+        }
+        failed |= !result.is_ok();
+        match json {
+            true => println!("{}", serde_json::to_string(&result.report(line)).unwrap()),
+            false => {
+                println!("{}", result.format(output_mode));
+                if result.is_ok() {
+                    for diagnostic in result.diagnostics(line) {
+                        eprintln!("{}", diagnostic);
+                    }
+                }
+            }
+        }
+    }
+    match failed {
+        true => ExitCode::FAILURE,
+        false => ExitCode::SUCCESS,
+    }
+}
@@ -1,13 +1,27 @@
+use clap::Parser;
+use std::io::IsTerminal;
+use std::process::ExitCode;
 use tec2_client::App;
+use tec2_client::cli::Cli;
 
 use tec2_client::ui::Tec2ClientRouter;
 
-fn main() -> color_eyre::Result<()> {
+fn main() -> color_eyre::Result<ExitCode> {
+    let mut cli = Cli::parse();
+    if let Err(message) = cli.load_layout() {
+        eprintln!("{}", message);
+        return Ok(ExitCode::FAILURE);
+    }
+    if let Some(command) = cli.command.take() {
+        return Ok(command.run(cli.json));
+    }
+    if cli.repl || !std::io::stdin().is_terminal() {
+        return Ok(cli.run_repl());
+    }
     color_eyre::install()?;
     let terminal = ratatui::init();
 
     let result = App::new::<Tec2ClientRouter>().run(terminal);
     ratatui::restore();
-    result
+    result.map(|_| ExitCode::SUCCESS)
 }
-
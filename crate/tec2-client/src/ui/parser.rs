@@ -1,20 +1,28 @@
 use crate::app::{AppUtil, Page};
-use lalrpop_util::ParseError;
 use ratatui::Frame;
 use ratatui::crossterm::event::{KeyCode, KeyEvent, KeyEventKind};
 use ratatui::layout::Alignment;
-use ratatui::prelude::{Constraint, Layout, Position, Text};
+use ratatui::prelude::{Color, Constraint, Layout, Position, Style, Text};
 use ratatui::widgets::{Block, List, ListItem, Paragraph, Wrap};
 use std::cmp::{max, min};
-use std::fmt::{Display, Formatter};
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::sync::{Arc, Mutex};
 use std::time::Duration;
-use tec2_parser::grammar;
-use tec2_parser::parser::parser::ExprParser;
+use tec2_parser::Severity;
+use tec2_parser::parser::format::{OutputMode, Padding, WordFormat, WordOrder};
+use tec2_parser::result::ParseResult;
 
 const USER_INPUT_PREFIX: &str = ">>> ";
 
+// 按<TAB>依次切换
+const OUTPUT_MODES: [OutputMode; 5] = [
+    OutputMode::Hex,
+    OutputMode::Words(WordFormat::new(Padding::Left, WordOrder::HighFirst)),
+    OutputMode::Words(WordFormat::new(Padding::Left, WordOrder::LowFirst)),
+    OutputMode::Words(WordFormat::new(Padding::Right, WordOrder::HighFirst)),
+    OutputMode::Words(WordFormat::new(Padding::Right, WordOrder::LowFirst)),
+];
+
 pub struct Parser {
     util: AppUtil,
     cursor: (usize, usize),
@@ -22,102 +30,23 @@ pub struct Parser {
     user_input: Arc<Mutex<String>>,
     controller_history: Arc<Mutex<Vec<String>>>,
     last_parse_result: Arc<Mutex<Option<ParseResult>>>,
+    output_mode: Arc<AtomicUsize>,
     run_flag: Arc<AtomicBool>,
 }
 
-#[derive(Debug)]
-enum ParseResult {
-    InvalidToken {
-        location: usize,
-    },
-    UnrecognizedEof {
-        location: usize,
-        expected: Vec<String>,
-    },
-    UnrecognizedToken {
-        token: (usize, String, usize),
-        expected: Vec<String>,
-    },
-    ExprParseError(tec2_parser::error::Error),
-    Result(String),
-}
-
-impl Display for ParseResult {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        match self {
-            ParseResult::InvalidToken { location } => {
-                write!(f, "在 \" {} \" 上出现不合法的token", location)
-            }
-            ParseResult::UnrecognizedEof { location, expected } => {
-                write!(
-                    f,
-                    "期望在 \" {} \" 获得Token: [{}]",
-                    location,
-                    expected.join(", ")
-                )
-            }
-            ParseResult::UnrecognizedToken { token, expected } => {
-                write!(
-                    f,
-                    "期望获得Token: [{}], 但是获取到 {}({}, {})",
-                    expected.join(", "),
-                    token.1,
-                    token.0,
-                    token.2
-                )
-            }
-            ParseResult::ExprParseError(error) => match error {
-                tec2_parser::error::Error::CanNotBeAchieved(reason) => {
-                    write!(f, "表达式解析错误: {}", reason)
-                }
-            },
-            ParseResult::Result(result) => {
-                write!(f, "{}", result)
-            }
-        }
-    }
-}
-
 impl Parser {
-    fn parse(input: &str) -> ParseResult {
-        match grammar::ExprParser::new().parse(input) {
-            Ok(expr) => {
-                let mut parser = ExprParser::new(expr);
-                match parser.parse() {
-                    Ok(_) => ParseResult::Result(hex::encode_upper(parser.hex())),
-                    Err(error) => ParseResult::ExprParseError(error),
-                }
-            }
-            Err(error) => match error {
-                ParseError::InvalidToken { location } => ParseResult::InvalidToken { location },
-                ParseError::UnrecognizedEof { location, expected } => {
-                    ParseResult::UnrecognizedEof { location, expected }
-                }
-                ParseError::UnrecognizedToken { token, expected } => {
-                    ParseResult::UnrecognizedToken {
-                        token: (token.0, token.1.to_string(), token.2),
-                        expected,
-                    }
-                }
-                ParseError::ExtraToken { token } => ParseResult::UnrecognizedToken {
-                    token: (token.0, token.1.to_string(), token.2),
-                    expected: vec![],
-                },
-                ParseError::User { .. } => unreachable!(),
-            },
-        }
-    }
-
     fn parse_enter(
         user_input: String,
         result: Arc<Mutex<Vec<String>>>,
         util: AppUtil,
         cursor: usize,
+        output_mode: OutputMode,
     ) {
-        let parse_result = Self::parse(&user_input[USER_INPUT_PREFIX.len()..user_input.len()]);
+        let parse_result =
+            ParseResult::parse(&user_input[USER_INPUT_PREFIX.len()..user_input.len()]);
         loop {
             if let Ok(mut result) = result.try_lock() {
-                result[cursor] = parse_result.to_string();
+                result[cursor] = parse_result.format(&output_mode);
                 util.update();
                 break;
             }
@@ -132,15 +61,15 @@ impl Parser {
     ) {
         let mut pre_user_input = String::new();
         while run_flag.load(Ordering::Relaxed) {
-            if let Ok(user_input) = user_input.try_lock() {
-                if pre_user_input != *user_input {
-                    pre_user_input = user_input.clone();
-                    let parse_result =
-                        Self::parse(&user_input[USER_INPUT_PREFIX.len()..user_input.len()]);
-                    if let Ok(mut result) = result.try_lock() {
-                        *result = Some(parse_result);
-                        util.update();
-                    }
+            if let Ok(user_input) = user_input.try_lock()
+                && pre_user_input != *user_input
+            {
+                pre_user_input = user_input.clone();
+                let parse_result =
+                    ParseResult::parse(&user_input[USER_INPUT_PREFIX.len()..user_input.len()]);
+                if let Ok(mut result) = result.try_lock() {
+                    *result = Some(parse_result);
+                    util.update();
                 }
             }
             std::thread::sleep(Duration::from_millis(500));
@@ -154,6 +83,7 @@ impl Page for Parser {
     {
         let user_input = Arc::new(Mutex::new(USER_INPUT_PREFIX.to_string()));
         let last_parse_result = Arc::new(Mutex::new(None));
+        let output_mode = Arc::new(AtomicUsize::new(0));
         let run_flag = Arc::new(AtomicBool::new(true));
         let (user_input_, last_parse_result_, util_, run_flag_) = (
             user_input.clone(),
@@ -173,6 +103,7 @@ impl Page for Parser {
             user_input,
             controller_history: Arc::new(Mutex::new(Vec::new())),
             last_parse_result,
+            output_mode,
             run_flag,
         }
     }
@@ -184,7 +115,10 @@ impl Page for Parser {
             Layout::vertical([Constraint::Fill(2), Constraint::Min(8)]).areas(frame.area());
         let [help_area, controller_area] =
             Layout::horizontal([Constraint::Min(8), Constraint::Fill(4)]).areas(up_area);
-        let help = Paragraph::new("1. 不要输入中文，输入中文会导致崩溃\n2. 按<ESC>返回主页")
+        let help = Paragraph::new(format!(
+            "1. 不要输入中文，输入中文会导致崩溃\n2. 按<ESC>返回主页\n3. 按<TAB>切换输出格式\n当前: {}",
+            OUTPUT_MODES[self.output_mode.load(Ordering::Relaxed)]
+        ))
             .block(
                 Block::bordered()
                     .title("帮助")
@@ -194,13 +128,7 @@ impl Page for Parser {
         self.max_cursor = (controller_area.y + controller_area.height - 3) as usize;
         let controller_history_len = controller_history.len();
         let controller = List::new({
-            let begin = {
-                if self.max_cursor > controller_history_len {
-                    0
-                } else {
-                    controller_history_len - self.max_cursor
-                }
-            };
+            let begin = controller_history_len.saturating_sub(self.max_cursor);
             let mut list = controller_history[begin..controller_history_len]
                 .iter()
                 .map(|item| ListItem::new(item.as_str()))
@@ -213,13 +141,36 @@ impl Page for Parser {
                 .title("控制台")
                 .title_alignment(Alignment::Center),
         );
-        let message = List::new([ListItem::new(Text::from({
+        let message = List::new({
             let last_parse_result = self.last_parse_result.lock().unwrap();
             match &*last_parse_result {
-                None => "".to_string(),
-                Some(data) => data.to_string(),
+                None => vec![],
+                Some(data) => {
+                    let output_mode = &OUTPUT_MODES[self.output_mode.load(Ordering::Relaxed)];
+                    let input = &user_input[USER_INPUT_PREFIX.len()..];
+                    // 出错时只有一条错误，成功时在结果下面列出警告和提示
+                    let diagnostics = match data.is_ok() {
+                        true => data.diagnostics(input),
+                        false => vec![],
+                    };
+                    let result = match data.is_ok() {
+                        true => Style::default(),
+                        false => severity_style(Severity::Error),
+                    };
+                    let mut list = vec![ListItem::new(Text::styled(
+                        data.format(output_mode),
+                        result,
+                    ))];
+                    list.extend(diagnostics.iter().map(|diagnostic| {
+                        ListItem::new(Text::styled(
+                            diagnostic.to_string(),
+                            severity_style(diagnostic.severity),
+                        ))
+                    }));
+                    list
+                }
             }
-        }))])
+        })
         .block(Block::bordered().title("消息"));
         frame.set_cursor_position(Position::new(
             controller_area.x + 1 + self.cursor.0 as u16,
@@ -235,11 +186,11 @@ impl Page for Parser {
             KeyEventKind::Press | KeyEventKind::Repeat => {
                 let mut user_input = self.user_input.lock().unwrap();
                 match event.code {
-                    KeyCode::Backspace | KeyCode::Delete => {
-                        if self.cursor.0 > USER_INPUT_PREFIX.len() {
-                            user_input.remove(self.cursor.0 - 1);
-                            self.cursor.0 -= 1;
-                        }
+                    KeyCode::Backspace | KeyCode::Delete
+                        if self.cursor.0 > USER_INPUT_PREFIX.len() =>
+                    {
+                        user_input.remove(self.cursor.0 - 1);
+                        self.cursor.0 -= 1;
                     }
                     KeyCode::Left => {
                         self.cursor.0 = max(USER_INPUT_PREFIX.len(), self.cursor.0 - 1);
@@ -256,19 +207,31 @@ impl Page for Parser {
                         controller_history.push("".to_string());
                         *user_input = USER_INPUT_PREFIX.to_string();
                         let user_input = controller_history[controller_history.len() - 3].clone();
-                        let (cursor, controller_history, util) = (
+                        let (cursor, controller_history, util, output_mode) = (
                             controller_history.len() - 2,
                             self.controller_history.clone(),
                             self.util.clone(),
+                            OUTPUT_MODES[self.output_mode.load(Ordering::Relaxed)],
                         );
                         std::thread::spawn(move || {
-                            Self::parse_enter(user_input, controller_history, util, cursor)
+                            Self::parse_enter(
+                                user_input,
+                                controller_history,
+                                util,
+                                cursor,
+                                output_mode,
+                            )
                         });
                     }
                     KeyCode::Char(char) => {
                         user_input.insert(self.cursor.0, char);
                         self.cursor.0 += 1;
                     }
+                    KeyCode::Tab => {
+                        let output_mode = self.output_mode.load(Ordering::Relaxed);
+                        self.output_mode
+                            .store((output_mode + 1) % OUTPUT_MODES.len(), Ordering::Relaxed);
+                    }
                     KeyCode::Esc => {
                         self.util.back();
                     }
@@ -280,6 +243,14 @@ impl Page for Parser {
     }
 }
 
+fn severity_style(severity: Severity) -> Style {
+    match severity {
+        Severity::Error => Style::default().fg(Color::Red),
+        Severity::Warning => Style::default().fg(Color::Yellow),
+        Severity::Note => Style::default().fg(Color::Cyan),
+    }
+}
+
 impl Drop for Parser {
     fn drop(&mut self) {
         self.run_flag.store(false, Ordering::Relaxed)
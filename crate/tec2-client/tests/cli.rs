@@ -0,0 +1,112 @@
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Command, Output, Stdio};
+
+fn tec2tool(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_tec2tool"))
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+/// 写到临时目录中的微程序文件
+fn source(name: &str, text: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("tec2tool-{}-{}.mc", std::process::id(), name));
+    std::fs::write(&path, text).unwrap();
+    path
+}
+
+/// 出错时退出码为1，只输出错误信息而不是panic
+fn assert_error(output: &Output, message: &str) {
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert_eq!(output.status.code(), Some(1), "{}", stderr);
+    assert!(output.stdout.is_empty());
+    assert!(stderr.contains(message), "{}", stderr);
+    assert!(!stderr.contains("panicked"), "{}", stderr);
+}
+
+#[test]
+fn test_unsupported_expr() {
+    for (index, (expr, message)) in [("Q -> IP", "IP不能被写入"), ("1 -> Q", "期望获得Token")]
+        .into_iter()
+        .enumerate()
+    {
+        assert_error(&tec2tool(&["encode", expr]), message);
+        assert_error(&tec2tool(&["explain", expr]), message);
+        let file = source(&format!("unsupported{}", index), &format!("{}\n", expr));
+        let output = tec2tool(&["build", file.to_str().unwrap()]);
+        std::fs::remove_file(&file).unwrap();
+        assert_error(&output, message);
+    }
+    // 拆分不了的连续运算
+    assert_error(&tec2tool(&["encode", "R1 + R2 + Q -> AR"]), "未知的表达式");
+}
+
+#[test]
+fn test_encode() {
+    let output = tec2tool(&["encode", "PC -> AR, PC + 1 -> PC"]);
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "000E00A0355402\n");
+
+    // 一条微指令完成不了时默认用Q暂存
+    let output = tec2tool(&["encode", "MEM -> MEM"]);
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "000E0000F00000\tMEM -> Q\n000E0010200010\tQ -> MEM\n"
+    );
+}
+
+#[test]
+fn test_trace_start() {
+    let file = source("trace", "SR -> AR\n");
+    let output = tec2tool(&["trace", "--start", "65535", file.to_str().unwrap()]);
+    std::fs::remove_file(&file).unwrap();
+    assert_error(&output, "微地址FFFFH超出了控存范围(0-3FFH)");
+}
+
+#[test]
+fn test_scratch() {
+    // 只用Q暂存时拆分不了
+    let file = source("scratch", "R1 + R2 + Q -> AR\n");
+    let path = file.to_str().unwrap();
+    let build = tec2tool(&["build", path, "--scratch", "R10"]);
+    let listing = tec2tool(&["listing", path, "--scratch", "R10"]);
+    let trace = tec2tool(&["trace", path, "--scratch", "R10", "--steps", "3"]);
+    let default = tec2tool(&["listing", path]);
+    std::fs::remove_file(&file).unwrap();
+    assert_eq!(
+        String::from_utf8_lossy(&build.stdout),
+        "000: 000E00B041A000\n001: 000E00B012A000\n002: 000E00900A0002\n"
+    );
+    assert!(listing.status.success());
+    assert!(String::from_utf8_lossy(&listing.stdout).contains("R10 + Q -> AR"));
+    assert_eq!(
+        String::from_utf8_lossy(&trace.stdout),
+        "000: R1 -> R10\n001: R10 + R2 -> R10\n002: R10 + Q -> AR\n003: \n"
+    );
+    assert_error(&default, "未知的表达式");
+}
+
+#[test]
+fn test_repl() {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_tec2tool"))
+        .arg("--repl")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(b"MEM -> MEM\nPC -> AR, PC + 1 -> PC\n")
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "000E0000F00000\tMEM -> Q\n000E0010200010\tQ -> MEM\n000E00A0355402\n"
+    );
+}
@@ -0,0 +1,168 @@
+use tec2_client::cli::Monitor;
+use tec2_parser::asm::InstructionSet;
+use tec2_parser::program::Program;
+use tec2_parser::sim::Machine;
+
+/// 只实现了MVRD、ADD、CALA和RET的微程序，CALA把返回地址放在R10，不用堆栈
+const MICROCODE: &str = "\
+FETCH: PC -> AR, PC + 1 -> PC
+       MEM -> IR
+       , CI=JMAP
+MVRD:  PC -> AR, PC + 1 -> PC
+       MEM -> DR, NEXT=FETCH, CC#=0
+ADD:   SR + DR -> DR, CarryFromALU, NEXT=FETCH, CC#=0
+CALA:  PC -> AR, PC + 1 -> PC
+       MEM -> Q
+       R5 -> R10
+       Q -> R5, NEXT=FETCH, CC#=0
+RET:   R10 -> R5, NEXT=FETCH, CC#=0
+";
+
+fn monitor() -> Monitor {
+    let program = Program::parse(MICROCODE).unwrap();
+    let map = [(0x88, "MVRD"), (0x00, "ADD"), (0xCE, "CALA"), (0x8F, "RET")]
+        .into_iter()
+        .map(|(opcode, label)| (opcode, program.label(label).unwrap()))
+        .collect();
+    let machine = Machine::new(program.image().unwrap(), map);
+    let fetch = program.label("FETCH").unwrap();
+    Monitor::new(machine, InstructionSet::tec2(), fetch, 10000)
+}
+
+fn execute(monitor: &mut Monitor, lines: &[&str]) -> String {
+    lines
+        .iter()
+        .map(|line| monitor.execute(line).unwrap())
+        .collect()
+}
+
+#[test]
+fn test_assemble_and_go() {
+    let mut monitor = monitor();
+    assert_eq!(execute(&mut monitor, &["A 100"]), "");
+    assert_eq!(monitor.prompt(), "0100: ");
+    execute(&mut monitor, &["MVRD R1, 5", "MVRD R2, 7", "ADD R2, R1"]);
+    assert_eq!(monitor.prompt(), "0105: ");
+    execute(&mut monitor, &["RET", ""]);
+    assert_eq!(monitor.prompt(), "> ");
+    assert_eq!(
+        &monitor.machine().memory[0x100..0x107],
+        [0x8810, 5, 0x8820, 7, 0x0021, 0x8F00, 0]
+    );
+
+    let output = execute(&mut monitor, &["U 100"]);
+    let lines = output.lines().collect::<Vec<_>>();
+    assert_eq!(lines.len(), 16);
+    assert!(lines[0].starts_with("0100  8810 0005"), "{}", lines[0]);
+    assert!(lines[0].contains("MVRD"), "{}", lines[0]);
+    assert!(lines[3].starts_with("0105  8F00"), "{}", lines[3]);
+    // 不带地址时接着上一次
+    assert!(lines[15].starts_with("0111  0000"), "{}", lines[15]);
+    assert!(execute(&mut monitor, &["U"]).starts_with("0112  "));
+
+    // 运行到RET为止
+    let output = execute(&mut monitor, &["G 100"]);
+    assert_eq!(monitor.machine().datapath.registers[2], 12);
+    assert_eq!(monitor.machine().pc(), 0x105);
+    assert!(output.contains("R1=0005 R2=000C"), "{}", output);
+    assert!(output.contains("PC=0105"), "{}", output);
+
+    // 已经停在RET上时不再执行
+    let cycles = monitor.machine().cycles;
+    execute(&mut monitor, &["G"]);
+    assert_eq!(monitor.machine().pc(), 0x105);
+    assert_eq!(monitor.machine().cycles, cycles);
+}
+
+#[test]
+fn test_edit_and_dump() {
+    let mut monitor = monitor();
+    execute(&mut monitor, &["E 200 4142 43 FFFFH"]);
+    assert_eq!(
+        &monitor.machine().memory[0x200..0x203],
+        [0x4142, 0x43, 0xFFFF]
+    );
+
+    // 不带字时逐个修改，空行结束
+    execute(&mut monitor, &["E 300"]);
+    assert_eq!(monitor.prompt(), "0300 0000: ");
+    execute(&mut monitor, &["1234", "5678"]);
+    assert_eq!(monitor.prompt(), "0302 0000: ");
+    execute(&mut monitor, &[""]);
+    assert_eq!(monitor.prompt(), "> ");
+    assert_eq!(&monitor.machine().memory[0x300..0x302], [0x1234, 0x5678]);
+
+    let output = execute(&mut monitor, &["D 200"]);
+    let lines = output.lines().collect::<Vec<_>>();
+    assert_eq!(lines.len(), 8);
+    assert_eq!(
+        lines[0],
+        "0200  4142 0043 FFFF 0000 0000 0000 0000 0000  AB.C............"
+    );
+    assert!(lines[7].starts_with("0238  "));
+    // 不带地址时接着上一次
+    assert!(execute(&mut monitor, &["D"]).starts_with("0240  "));
+}
+
+#[test]
+fn test_registers() {
+    let mut monitor = monitor();
+    let output = execute(&mut monitor, &["R R3 55", "r sp 1000"]);
+    assert_eq!(monitor.machine().datapath.registers[3], 0x55);
+    assert_eq!(monitor.machine().datapath.registers[4], 0x1000);
+    assert!(output.contains("R3=0055 SP=1000"), "{}", output);
+    assert!(output.contains("C=0 Z=0 V=0 S=0"), "{}", output);
+}
+
+#[test]
+fn test_step() {
+    let mut monitor = monitor();
+    // 100: MVRD R1, 5; CALA 200; MVRD R2, 7
+    // 200: MVRD R3, 9; RET
+    execute(
+        &mut monitor,
+        &[
+            "E 100 8810 5 CE00 200 8820 7",
+            "E 200 8830 9 8F00",
+            "R PC 100",
+        ],
+    );
+    let output = execute(&mut monitor, &["T"]);
+    assert_eq!(monitor.machine().datapath.registers[1], 5);
+    assert!(output.contains("PC=0102"), "{}", output);
+
+    // P不进入子程序
+    execute(&mut monitor, &["P"]);
+    assert_eq!(monitor.machine().pc(), 0x104);
+    assert_eq!(monitor.machine().datapath.registers[3], 9);
+
+    // T进入子程序，可以一次执行多条
+    execute(&mut monitor, &["R PC 102", "T"]);
+    assert_eq!(monitor.machine().pc(), 0x200);
+    let output = execute(&mut monitor, &["T 2"]);
+    assert_eq!(output.matches("PC=").count(), 2);
+    assert_eq!(monitor.machine().pc(), 0x104);
+}
+
+#[test]
+fn test_error() {
+    let mut monitor = monitor();
+    let error = |monitor: &mut Monitor, line: &str| monitor.execute(line).unwrap_err();
+    assert_eq!(error(&mut monitor, "X"), "未知的命令: X");
+    assert_eq!(error(&mut monitor, "E"), "E命令需要地址");
+    assert_eq!(error(&mut monitor, "D 12345"), "不合法的十六进制数: 12345");
+    assert_eq!(error(&mut monitor, "E 100 XYZ"), "不合法的十六进制数: XYZ");
+    assert_eq!(error(&mut monitor, "R R16 1"), "未知的寄存器: R16");
+    assert_eq!(error(&mut monitor, "R PC"), "R命令的格式为: R 寄存器 值");
+    assert_eq!(monitor.machine().datapath.registers[5], 0);
+
+    // 汇编出错时留在汇编模式
+    execute(&mut monitor, &["A 100"]);
+    assert!(monitor.execute("NOP R1").is_err());
+    assert_eq!(monitor.prompt(), "0100: ");
+    execute(&mut monitor, &[""]);
+
+    // 操作码10H没有对应的微程序入口
+    execute(&mut monitor, &["E 0 1000", "R PC 0"]);
+    assert_eq!(error(&mut monitor, "T"), "操作码 10H 没有对应的微程序入口");
+}
@@ -0,0 +1,174 @@
+use proc_macro::TokenStream;
+use proc_macro2::Span;
+use quote::quote;
+use syn::parse::ParseStream;
+use syn::{
+    Attribute, Data, DeriveInput, Expr, ExprLit, Fields, Ident, Lit, LitInt, Meta, Token,
+    parse_macro_input,
+};
+
+/// 为微指令字段的枚举生成`ToInstrument`、`Display`和`TryFrom<u64>`
+///
+/// `Display`使用每个值的文档注释，`TryFrom<u64>`用于解码
+///
+/// ```ignore
+/// #[derive(ToInstrument)]
+/// #[field(MI86, width = 3)]
+/// pub enum Mi86 {
+///     /// F->Q, Y=F
+///     FQF = 0,
+///     /// 无, Y=F
+///     NONE = 1,
+/// }
+/// ```
+#[proc_macro_derive(ToInstrument, attributes(field))]
+pub fn derive_to_instrument(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+struct Variant {
+    ident: Ident,
+    value: u64,
+    doc: String,
+}
+
+fn expand(input: DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
+    let name = &input.ident;
+    let (field, width) = parse_field(&input)?;
+    let variants = parse_variants(&input, width)?;
+
+    let idents = variants.iter().map(|v| &v.ident).collect::<Vec<_>>();
+    let values = variants.iter().map(|v| v.value as u8).collect::<Vec<_>>();
+    let values_u64 = variants.iter().map(|v| v.value).collect::<Vec<_>>();
+    let docs = variants.iter().map(|v| &v.doc).collect::<Vec<_>>();
+    let width = width as usize;
+    let field_name = field.to_string();
+
+    Ok(quote! {
+        impl ::tec2_parser::parser::instrument::ToInstrument for #name {
+            fn to_instrument(&self) -> ::std::boxed::Box<[::tec2_parser::parser::instrument::Instrument]> {
+                let value: u8 = match self {
+                    #(Self::#idents => #values,)*
+                };
+                ::std::boxed::Box::new([::tec2_parser::parser::instrument::Instrument::#field(
+                    ::tec2_parser::to_bytes!(value, #width),
+                )])
+            }
+        }
+
+        impl ::std::fmt::Display for #name {
+            fn fmt(&self, f: &mut ::std::fmt::Formatter<'_>) -> ::std::fmt::Result {
+                f.write_str(match self {
+                    #(Self::#idents => #docs,)*
+                })
+            }
+        }
+
+        impl ::std::convert::TryFrom<u64> for #name {
+            type Error = ::tec2_parser::Error;
+
+            fn try_from(value: u64) -> ::std::result::Result<Self, Self::Error> {
+                match value {
+                    #(#values_u64 => ::std::result::Result::Ok(Self::#idents),)*
+                    _ => ::std::result::Result::Err(::tec2_parser::Error::InvalidFieldValue {
+                        field: #field_name,
+                        value,
+                    }),
+                }
+            }
+        }
+    })
+}
+
+/// 解析`#[field(MI86, width = 3)]`
+fn parse_field(input: &DeriveInput) -> syn::Result<(Ident, u32)> {
+    let attr = input
+        .attrs
+        .iter()
+        .find(|attr| attr.path().is_ident("field"))
+        .ok_or_else(|| {
+            syn::Error::new(
+                Span::call_site(),
+                "缺少#[field(字段, width = 位数)]，例如#[field(MI86, width = 3)]",
+            )
+        })?;
+    attr.parse_args_with(|input: ParseStream| {
+        let field: Ident = input.parse()?;
+        input.parse::<Token![,]>()?;
+        let key: Ident = input.parse()?;
+        if key != "width" {
+            return Err(syn::Error::new(key.span(), "期望width = 位数"));
+        }
+        input.parse::<Token![=]>()?;
+        let width: LitInt = input.parse()?;
+        let value = width.base10_parse::<u32>()?;
+        if value == 0 || value > 8 {
+            return Err(syn::Error::new(width.span(), "字段宽度必须在1到8位之间"));
+        }
+        Ok((field, value))
+    })
+}
+
+fn parse_variants(input: &DeriveInput, width: u32) -> syn::Result<Vec<Variant>> {
+    let Data::Enum(data) = &input.data else {
+        return Err(syn::Error::new(
+            input.ident.span(),
+            "ToInstrument只能用于枚举",
+        ));
+    };
+    let mut next = 0u64;
+    let mut variants = vec![];
+    for variant in data.variants.iter() {
+        if !matches!(variant.fields, Fields::Unit) {
+            return Err(syn::Error::new(
+                variant.ident.span(),
+                "ToInstrument只支持不带数据的枚举值",
+            ));
+        }
+        let value = match &variant.discriminant {
+            Some((
+                _,
+                Expr::Lit(ExprLit {
+                    lit: Lit::Int(lit), ..
+                }),
+            )) => lit.base10_parse::<u64>()?,
+            Some((_, expr)) => {
+                return Err(syn::Error::new_spanned(expr, "枚举值必须是整数字面量"));
+            }
+            None => next,
+        };
+        if value >> width != 0 {
+            return Err(syn::Error::new(
+                variant.ident.span(),
+                format!("{}超出了{}位字段的范围", value, width),
+            ));
+        }
+        next = value + 1;
+        variants.push(Variant {
+            ident: variant.ident.clone(),
+            value,
+            doc: doc(&variant.attrs).unwrap_or_else(|| variant.ident.to_string()),
+        });
+    }
+    Ok(variants)
+}
+
+fn doc(attrs: &[Attribute]) -> Option<String> {
+    let lines = attrs
+        .iter()
+        .filter_map(|attr| match &attr.meta {
+            Meta::NameValue(meta) if meta.path.is_ident("doc") => match &meta.value {
+                Expr::Lit(ExprLit {
+                    lit: Lit::Str(lit), ..
+                }) => Some(lit.value().trim().to_string()),
+                _ => None,
+            },
+            _ => None,
+        })
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<_>>();
+    (!lines.is_empty()).then(|| lines.join(" "))
+}
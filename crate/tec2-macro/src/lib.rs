@@ -1 +1,52 @@
+use proc_macro::TokenStream;
+use quote::quote;
+use syn::{LitStr, parse_macro_input};
+use tec2_parser::result::ParseResult;
 
+/// 在编译期把表达式编码为微指令，得到`u64`常量(低56位有效)
+///
+/// ```
+/// const WORD: u64 = tec2_macro::micro!("MEM + Q -> AR, CarryFromALU");
+/// assert_eq!(WORD, 0x000E0110E00002);
+/// ```
+///
+/// 表达式有误时在字符串的位置报编译错误
+///
+/// ```compile_fail
+/// const WORD: u64 = tec2_macro::micro!("Q + Q -> AR");
+/// ```
+#[proc_macro]
+pub fn micro(input: TokenStream) -> TokenStream {
+    let lit = parse_macro_input!(input as LitStr);
+    match encode(&lit) {
+        Ok(hex) => {
+            let value = hex
+                .iter()
+                .fold(0u64, |value, &byte| (value << 8) | byte as u64);
+            quote!(#value).into()
+        }
+        Err(error) => error.to_compile_error().into(),
+    }
+}
+
+/// 在编译期把表达式编码为微指令，得到字节数组常量(TEC-2格式为`[u8; 7]`)
+///
+/// ```
+/// const WORD: [u8; 7] = tec2_macro::micro_bytes!("SR -> AR");
+/// assert_eq!(WORD, [0x00, 0x0E, 0x00, 0x90, 0x40, 0x00, 0x82]);
+/// ```
+#[proc_macro]
+pub fn micro_bytes(input: TokenStream) -> TokenStream {
+    let lit = parse_macro_input!(input as LitStr);
+    match encode(&lit) {
+        Ok(hex) => quote!([#(#hex),*]).into(),
+        Err(error) => error.to_compile_error().into(),
+    }
+}
+
+fn encode(lit: &LitStr) -> syn::Result<Vec<u8>> {
+    match ParseResult::parse(&lit.value()) {
+        ParseResult::Result(encoding) => Ok(encoding.hex),
+        error => Err(syn::Error::new(lit.span(), error.to_string())),
+    }
+}
@@ -0,0 +1,5 @@
+/// 表达式有误时在字符串的位置报编译错误
+#[test]
+fn test_compile_fail() {
+    trybuild::TestCases::new().compile_fail("tests/ui/*.rs");
+}
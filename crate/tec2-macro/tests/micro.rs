@@ -0,0 +1,13 @@
+use tec2_macro::{micro, micro_bytes};
+
+const FETCH: [u64; 2] = [micro!("PC -> AR, PC + 1 -> PC"), micro!("MEM -> DR")];
+
+#[test]
+fn test_micro() {
+    assert_eq!(FETCH, [0x000E00A0355402, 0x000E0030F00008]);
+    assert_eq!(micro!("MEM - Q -> Q, CarryFromALU"), 0x000E0102E00400);
+    assert_eq!(
+        micro_bytes!("DR -> MEM, CC#=0"),
+        [0x29, 0x03, 0x00, 0x10, 0x30, 0x00, 0x18]
+    );
+}
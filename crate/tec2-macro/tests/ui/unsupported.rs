@@ -0,0 +1,7 @@
+use tec2_macro::micro;
+
+const WORD: u64 = micro!("PC + Q -> AR");
+
+fn main() {
+    let _ = WORD;
+}
@@ -0,0 +1,319 @@
+use crate::asm::{Format, InstructionSet};
+use crate::program::{is_label, org_operand, parse_number, strip_comment};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::{Display, Formatter};
+
+/// 汇编后的一行
+#[derive(Debug, Clone, Serialize)]
+pub struct AsmLine {
+    pub line: usize, // 从1开始的行号
+    pub address: u16,
+    pub labels: Vec<String>,
+    pub words: Vec<u16>,
+    pub source: String,
+}
+
+/// TEC-2汇编程序
+///
+/// ```text
+/// ; 注释
+///         ORG 0100H
+/// START:  MVRD R1, 5
+///         ADD R2, R1
+///         JR START
+/// DATA:   DW 1234H, START
+/// ```
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Assembly {
+    lines: Vec<AsmLine>,
+    labels: HashMap<String, u16>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "kind", content = "detail", rename_all = "snake_case")]
+pub enum AsmErrorKind {
+    UnknownMnemonic(String),
+    InvalidOperand(String),
+    InvalidLabel(String),
+    DuplicateLabel(String),
+    UndefinedLabel(String),
+    InvalidNumber(String),
+    OffsetOutOfRange(i32),
+    AddressOverlap(u16),
+}
+
+#[derive(Debug, Serialize)]
+pub struct AsmError {
+    pub line: usize,
+    pub kind: AsmErrorKind,
+}
+
+struct Statement<'a> {
+    line: usize,
+    address: u16,
+    labels: Vec<String>,
+    mnemonic: &'a str,
+    operands: Vec<&'a str>,
+    source: &'a str,
+}
+
+impl Assembly {
+    /// 使用TEC-2的基本指令汇编
+    pub fn assemble(input: &str) -> Result<Self, AsmError> {
+        Self::assemble_with(input, &InstructionSet::tec2())
+    }
+
+    pub fn assemble_with(input: &str, set: &InstructionSet) -> Result<Self, AsmError> {
+        let (statements, labels) = Self::scan(input, set)?;
+        let mut lines = Vec::with_capacity(statements.len());
+        let mut used = BTreeMap::new();
+        for statement in statements {
+            let error = |kind| AsmError {
+                line: statement.line,
+                kind,
+            };
+            let words = encode(&statement, set, &labels).map_err(error)?;
+            for i in 0..words.len() as u16 {
+                let address = statement.address.wrapping_add(i);
+                if used.insert(address, statement.line).is_some() {
+                    return Err(error(AsmErrorKind::AddressOverlap(address)));
+                }
+            }
+            lines.push(AsmLine {
+                line: statement.line,
+                address: statement.address,
+                labels: statement.labels,
+                words,
+                source: statement.source.to_string(),
+            });
+        }
+        Ok(Self { lines, labels })
+    }
+
+    /// 第一遍扫描，确定每条语句的地址和标号
+    fn scan<'a>(
+        input: &'a str,
+        set: &InstructionSet,
+    ) -> Result<(Vec<Statement<'a>>, HashMap<String, u16>), AsmError> {
+        let mut statements = vec![];
+        let mut labels = HashMap::new();
+        let mut pending_labels: Vec<String> = vec![];
+        let mut address = 0u16;
+        for (index, source) in input.lines().enumerate() {
+            let line = index + 1;
+            let error = |kind| AsmError { line, kind };
+            let mut text = strip_comment(source).trim();
+            if let Some(operand) = org_operand(text) {
+                address = parse_number(operand)
+                    .and_then(|x| u16::try_from(x).ok())
+                    .ok_or_else(|| error(AsmErrorKind::InvalidNumber(operand.to_string())))?;
+                continue;
+            }
+            while let Some((label, rest)) = text.split_once(':') {
+                let label = label.trim();
+                if !is_label(label) {
+                    return Err(error(AsmErrorKind::InvalidLabel(label.to_string())));
+                }
+                if labels.contains_key(label) || pending_labels.iter().any(|x| x == label) {
+                    return Err(error(AsmErrorKind::DuplicateLabel(label.to_string())));
+                }
+                pending_labels.push(label.to_string());
+                text = rest.trim();
+            }
+            if text.is_empty() {
+                continue;
+            }
+            let (mnemonic, operands) = text.split_once(char::is_whitespace).unwrap_or((text, ""));
+            let operands = operands
+                .split(',')
+                .map(str::trim)
+                .filter(|x| !x.is_empty())
+                .collect::<Vec<_>>();
+            let size = if mnemonic.eq_ignore_ascii_case("DW") {
+                operands.len() as u16
+            } else {
+                set.find(mnemonic)
+                    .ok_or_else(|| error(AsmErrorKind::UnknownMnemonic(mnemonic.to_string())))?
+                    .format
+                    .words()
+            };
+            for label in pending_labels.iter() {
+                labels.insert(label.clone(), address);
+            }
+            statements.push(Statement {
+                line,
+                address,
+                labels: std::mem::take(&mut pending_labels),
+                mnemonic,
+                operands,
+                source: source.trim(),
+            });
+            address = address.wrapping_add(size);
+        }
+        for label in pending_labels {
+            labels.insert(label, address);
+        }
+        Ok((statements, labels))
+    }
+
+    /// 按源代码顺序排列的语句
+    pub fn lines(&self) -> &[AsmLine] {
+        &self.lines
+    }
+
+    pub fn label(&self, name: &str) -> Option<u16> {
+        self.labels.get(name).copied()
+    }
+
+    /// 按地址排列的主存内容
+    pub fn image(&self) -> BTreeMap<u16, u16> {
+        self.lines
+            .iter()
+            .flat_map(|line| {
+                line.words
+                    .iter()
+                    .enumerate()
+                    .map(|(i, word)| (line.address.wrapping_add(i as u16), *word))
+            })
+            .collect()
+    }
+
+    /// 地址、机器码和源代码对照的列表
+    pub fn listing(&self) -> String {
+        self.lines
+            .iter()
+            .map(|line| {
+                let words = line
+                    .words
+                    .iter()
+                    .map(|word| format!("{:04X}", word))
+                    .collect::<Vec<_>>()
+                    .join(" ");
+                format!("{:04X}  {:<10}  {}\n", line.address, words, line.source)
+            })
+            .collect()
+    }
+}
+
+fn encode(
+    statement: &Statement,
+    set: &InstructionSet,
+    labels: &HashMap<String, u16>,
+) -> Result<Vec<u16>, AsmErrorKind> {
+    let operands = &statement.operands;
+    if statement.mnemonic.eq_ignore_ascii_case("DW") {
+        return operands.iter().map(|x| value(x, labels)).collect();
+    }
+    // 第一遍扫描已经检查过助记符
+    let def = set.find(statement.mnemonic).unwrap();
+    let count = match def.format {
+        Format::None => 0,
+        Format::Dr | Format::Sr | Format::Offset | Format::Port | Format::Address => 1,
+        _ => 2,
+    };
+    if operands.len() != count {
+        return Err(AsmErrorKind::InvalidOperand(operands.join(", ")));
+    }
+    let opcode = (def.opcode as u16) << 8;
+    let words = match def.format {
+        Format::None => vec![opcode],
+        Format::DrSr => vec![opcode | register(operands[0])? << 4 | register(operands[1])?],
+        Format::Dr => vec![opcode | register(operands[0])? << 4],
+        Format::Sr => vec![opcode | register(operands[0])?],
+        Format::DrIndirect => {
+            vec![opcode | register(operands[0])? << 4 | register(indirect(operands[1])?)?]
+        }
+        Format::IndirectSr => {
+            vec![opcode | register(indirect(operands[0])?)? << 4 | register(operands[1])?]
+        }
+        Format::Offset => {
+            let target = value(operands[0], labels)?;
+            let offset = target as i32 - (statement.address as i32 + 1);
+            if !(-128..=127).contains(&offset) {
+                return Err(AsmErrorKind::OffsetOutOfRange(offset));
+            }
+            vec![opcode | (offset as u8) as u16]
+        }
+        Format::Port => {
+            let port = value(operands[0], labels)?;
+            if port > 0xFF {
+                return Err(AsmErrorKind::InvalidOperand(operands[0].to_string()));
+            }
+            vec![opcode | port]
+        }
+        Format::Address => vec![opcode, value(operands[0], labels)?],
+        Format::DrImmediate => vec![
+            opcode | register(operands[0])? << 4,
+            value(operands[1], labels)?,
+        ],
+    };
+    Ok(words)
+}
+
+/// R0-R15，以及SP(R4)、PC(R5)、IP(R6)
+pub fn register(text: &str) -> Result<u16, AsmErrorKind> {
+    let text = text.trim().to_ascii_uppercase();
+    let number = match text.as_str() {
+        "SP" => Some(4),
+        "PC" => Some(5),
+        "IP" => Some(6),
+        _ => text.strip_prefix('R').and_then(|x| x.parse::<u16>().ok()),
+    };
+    number
+        .filter(|x| *x < 16)
+        .ok_or(AsmErrorKind::InvalidOperand(text))
+}
+
+fn indirect(text: &str) -> Result<&str, AsmErrorKind> {
+    text.strip_prefix('[')
+        .and_then(|x| x.strip_suffix(']'))
+        .ok_or_else(|| AsmErrorKind::InvalidOperand(text.to_string()))
+}
+
+/// 数字或者标号，负数按补码
+fn value(text: &str, labels: &HashMap<String, u16>) -> Result<u16, AsmErrorKind> {
+    if is_label(text) && parse_number(text).is_none() {
+        return labels
+            .get(text)
+            .copied()
+            .ok_or_else(|| AsmErrorKind::UndefinedLabel(text.to_string()));
+    }
+    let (negative, number) = match text.strip_prefix('-') {
+        Some(number) => (true, number),
+        None => (false, text),
+    };
+    let number = parse_number(number)
+        .and_then(|x| u16::try_from(x).ok())
+        .ok_or_else(|| AsmErrorKind::InvalidNumber(text.to_string()))?;
+    Ok(if negative {
+        number.wrapping_neg()
+    } else {
+        number
+    })
+}
+
+impl Display for AsmErrorKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AsmErrorKind::UnknownMnemonic(mnemonic) => write!(f, "未知的指令: {}", mnemonic),
+            AsmErrorKind::InvalidOperand(operand) => write!(f, "不合法的操作数: {}", operand),
+            AsmErrorKind::InvalidLabel(label) => write!(f, "不合法的标号: {}", label),
+            AsmErrorKind::DuplicateLabel(label) => write!(f, "标号重复定义: {}", label),
+            AsmErrorKind::UndefinedLabel(label) => write!(f, "未定义的标号: {}", label),
+            AsmErrorKind::InvalidNumber(number) => write!(f, "不合法的数字: {}", number),
+            AsmErrorKind::OffsetOutOfRange(offset) => {
+                write!(f, "相对转移的偏移超出范围(-128到127): {}", offset)
+            }
+            AsmErrorKind::AddressOverlap(address) => {
+                write!(f, "地址 {:04X}H 被重复使用", address)
+            }
+        }
+    }
+}
+
+impl Display for AsmError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "第{}行: {}", self.line, self.kind)
+    }
+}
@@ -0,0 +1,70 @@
+use crate::asm::{Format, InstructionSet};
+use serde::Serialize;
+
+/// 反汇编得到的一条指令
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DisasmLine {
+    pub address: u16,
+    pub words: Vec<u16>,
+    pub text: String,
+    pub known: bool, // 操作码不在指令集中时为false，text为DW
+}
+
+impl InstructionSet {
+    /// 从address开始依次反汇编，未知的操作码输出为DW
+    pub fn disassemble(&self, address: u16, words: &[u16]) -> Vec<DisasmLine> {
+        let mut lines = vec![];
+        let mut offset = 0;
+        while let Some(line) = self.decode(address.wrapping_add(offset as u16), &words[offset..]) {
+            offset += line.words.len();
+            lines.push(line);
+        }
+        lines
+    }
+
+    /// 反汇编words开头的一条指令，双字指令缺少第二个字时按未知处理，words为空时为None
+    pub fn decode(&self, address: u16, words: &[u16]) -> Option<DisasmLine> {
+        let &word = words.first()?;
+        let unknown = || DisasmLine {
+            address,
+            words: vec![word],
+            text: format!("DW {:04X}H", word),
+            known: false,
+        };
+        let Some(def) = self.find_opcode((word >> 8) as u8) else {
+            return Some(unknown());
+        };
+        let size = def.format.words() as usize;
+        if words.len() < size {
+            return Some(unknown());
+        }
+        let dr = (word >> 4) & 0xF;
+        let sr = word & 0xF;
+        let operands = match def.format {
+            Format::None => String::new(),
+            Format::DrSr => format!("R{}, R{}", dr, sr),
+            Format::Dr => format!("R{}", dr),
+            Format::Sr => format!("R{}", sr),
+            Format::DrIndirect => format!("R{}, [R{}]", dr, sr),
+            Format::IndirectSr => format!("[R{}], R{}", dr, sr),
+            Format::Offset => {
+                let target = address
+                    .wrapping_add(1)
+                    .wrapping_add(word as u8 as i8 as u16);
+                format!("{:04X}H", target)
+            }
+            Format::Port => format!("{:02X}H", word & 0xFF),
+            Format::Address => format!("{:04X}H", words[1]),
+            Format::DrImmediate => format!("R{}, {:04X}H", dr, words[1]),
+        };
+        Some(DisasmLine {
+            address,
+            words: words[..size].to_vec(),
+            text: match operands.is_empty() {
+                true => def.mnemonic.clone(),
+                false => format!("{} {}", def.mnemonic, operands),
+            },
+            known: true,
+        })
+    }
+}
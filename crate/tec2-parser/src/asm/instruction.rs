@@ -0,0 +1,248 @@
+use crate::program::{Program, is_label, parse_number};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt::{Display, Formatter};
+use std::path::Path;
+
+/// 机器指令的格式，操作码都在IR15-8
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Format {
+    None,        // 无操作数
+    DrSr,        // DR, SR，DR在IR7-4，SR在IR3-0
+    Dr,          // DR
+    Sr,          // SR
+    DrIndirect,  // DR, [SR]
+    IndirectSr,  // [DR], SR
+    Offset,      // 相对转移，IR7-0为相对下一条指令的偏移
+    Port,        // I/O端口在IR7-0
+    Address,     // 第二个字为地址
+    DrImmediate, // DR, 第二个字为立即数
+}
+
+/// 指令的助记符、操作码和格式
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InstructionDef {
+    pub mnemonic: String,
+    pub opcode: u8,
+    pub format: Format,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub entry: Option<String>, // 微程序入口的标号或地址，用于生成JMAP的映射
+}
+
+/// 汇编和反汇编使用的指令集
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct InstructionSet {
+    instructions: Vec<InstructionDef>,
+}
+
+/// 指令定义文件，把新指令的操作码、格式和微程序入口写在一起
+///
+/// ```toml
+/// base = true # 包含TEC-2的基本指令，默认为true
+///
+/// [[instructions]]
+/// mnemonic = "ADDM"
+/// opcode = 0xE0
+/// format = "dr_indirect"
+/// entry = "ADDM"
+/// ```
+#[derive(Debug, Clone, Deserialize)]
+struct InstructionFile {
+    #[serde(default = "default_base")]
+    base: bool,
+    #[serde(default)]
+    instructions: Vec<InstructionDef>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "kind", content = "detail", rename_all = "snake_case")]
+pub enum DefinitionError {
+    Io(String),
+    Syntax(String),
+    InvalidMnemonic(String),
+    DuplicateMnemonic(String),
+    DuplicateOpcode(u8),
+    UndefinedEntry(String, String), // (助记符, 入口)
+}
+
+const TEC2_INSTRUCTIONS: [(&str, u8, Format); 29] = [
+    ("ADD", 0x00, Format::DrSr),
+    ("SUB", 0x01, Format::DrSr),
+    ("AND", 0x02, Format::DrSr),
+    ("CMP", 0x03, Format::DrSr),
+    ("XOR", 0x04, Format::DrSr),
+    ("TEST", 0x05, Format::DrSr),
+    ("OR", 0x06, Format::DrSr),
+    ("MVRR", 0x07, Format::DrSr),
+    ("DEC", 0x08, Format::Dr),
+    ("INC", 0x09, Format::Dr),
+    ("SHL", 0x0A, Format::Dr),
+    ("SHR", 0x0B, Format::Dr),
+    ("JR", 0x41, Format::Offset),
+    ("JRC", 0x44, Format::Offset),
+    ("JRNC", 0x45, Format::Offset),
+    ("JRZ", 0x46, Format::Offset),
+    ("JRNZ", 0x47, Format::Offset),
+    ("JMPA", 0x80, Format::Address),
+    ("LDRR", 0x81, Format::DrIndirect),
+    ("IN", 0x82, Format::Port),
+    ("STRR", 0x83, Format::IndirectSr),
+    ("PSHF", 0x84, Format::None),
+    ("PUSH", 0x85, Format::Sr),
+    ("OUT", 0x86, Format::Port),
+    ("POP", 0x87, Format::Dr),
+    ("MVRD", 0x88, Format::DrImmediate),
+    ("POPF", 0x8C, Format::None),
+    ("RET", 0x8F, Format::None),
+    ("CALA", 0xCE, Format::Address),
+];
+
+impl Format {
+    /// 指令占用的字数
+    pub fn words(&self) -> u16 {
+        match self {
+            Format::Address | Format::DrImmediate => 2,
+            _ => 1,
+        }
+    }
+}
+
+impl InstructionSet {
+    /// TEC-2的基本指令
+    pub fn tec2() -> Self {
+        Self {
+            instructions: TEC2_INSTRUCTIONS
+                .iter()
+                .map(|&(mnemonic, opcode, format)| InstructionDef {
+                    mnemonic: mnemonic.to_string(),
+                    opcode,
+                    format,
+                    entry: None,
+                })
+                .collect(),
+        }
+    }
+
+    pub fn from_toml(input: &str) -> Result<Self, DefinitionError> {
+        let file: InstructionFile =
+            toml::from_str(input).map_err(|error| DefinitionError::Syntax(error.to_string()))?;
+        Self::from_file(file)
+    }
+
+    pub fn from_json(input: &str) -> Result<Self, DefinitionError> {
+        let file: InstructionFile = serde_json::from_str(input)
+            .map_err(|error| DefinitionError::Syntax(error.to_string()))?;
+        Self::from_file(file)
+    }
+
+    /// 按扩展名读取.toml或.json文件
+    pub fn load(path: &Path) -> Result<Self, DefinitionError> {
+        let input = std::fs::read_to_string(path)
+            .map_err(|error| DefinitionError::Io(format!("{}: {}", path.display(), error)))?;
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some(ext) if ext.eq_ignore_ascii_case("json") => Self::from_json(&input),
+            _ => Self::from_toml(&input),
+        }
+    }
+
+    /// 文件中的指令之间不能重复，与基本指令的操作码或助记符相同时替换基本指令
+    fn from_file(file: InstructionFile) -> Result<Self, DefinitionError> {
+        let mut set = Self::from_instructions(file.instructions)?;
+        if file.base {
+            let user = set.instructions;
+            set = Self::tec2();
+            set.instructions.retain(|def| {
+                !user.iter().any(|x| {
+                    x.opcode == def.opcode || x.mnemonic.eq_ignore_ascii_case(&def.mnemonic)
+                })
+            });
+            set.instructions.extend(user);
+        }
+        Ok(set)
+    }
+
+    pub fn from_instructions(instructions: Vec<InstructionDef>) -> Result<Self, DefinitionError> {
+        for (i, def) in instructions.iter().enumerate() {
+            if !is_label(&def.mnemonic)
+                || ["DW", "ORG"]
+                    .iter()
+                    .any(|x| x.eq_ignore_ascii_case(&def.mnemonic))
+            {
+                return Err(DefinitionError::InvalidMnemonic(def.mnemonic.clone()));
+            }
+            for other in instructions[..i].iter() {
+                if other.mnemonic.eq_ignore_ascii_case(&def.mnemonic) {
+                    return Err(DefinitionError::DuplicateMnemonic(def.mnemonic.clone()));
+                }
+                if other.opcode == def.opcode {
+                    return Err(DefinitionError::DuplicateOpcode(def.opcode));
+                }
+            }
+        }
+        Ok(Self { instructions })
+    }
+
+    pub fn instructions(&self) -> &[InstructionDef] {
+        &self.instructions
+    }
+
+    /// 按助记符查找，不区分大小写
+    pub fn find(&self, mnemonic: &str) -> Option<&InstructionDef> {
+        self.instructions
+            .iter()
+            .find(|def| def.mnemonic.eq_ignore_ascii_case(mnemonic))
+    }
+
+    pub fn find_opcode(&self, opcode: u8) -> Option<&InstructionDef> {
+        self.instructions.iter().find(|def| def.opcode == opcode)
+    }
+
+    /// 按微程序中的标号生成映射ROM，即JMAP使用的操作码到微程序入口的映射
+    pub fn map(&self, program: &Program) -> Result<BTreeMap<u8, u16>, DefinitionError> {
+        self.instructions
+            .iter()
+            .filter_map(|def| def.entry.as_ref().map(|entry| (def, entry)))
+            .map(|(def, entry)| {
+                program
+                    .label(entry)
+                    .or_else(|| parse_number(entry).and_then(|x| u16::try_from(x).ok()))
+                    .map(|address| (def.opcode, address))
+                    .ok_or_else(|| {
+                        DefinitionError::UndefinedEntry(def.mnemonic.clone(), entry.clone())
+                    })
+            })
+            .collect()
+    }
+}
+
+fn default_base() -> bool {
+    true
+}
+
+impl Default for InstructionSet {
+    fn default() -> Self {
+        Self::tec2()
+    }
+}
+
+impl Display for DefinitionError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DefinitionError::Io(message) => write!(f, "无法读取指令定义: {}", message),
+            DefinitionError::Syntax(message) => write!(f, "指令定义有误: {}", message),
+            DefinitionError::InvalidMnemonic(mnemonic) => {
+                write!(f, "不合法的助记符: {}", mnemonic)
+            }
+            DefinitionError::DuplicateMnemonic(mnemonic) => {
+                write!(f, "助记符重复定义: {}", mnemonic)
+            }
+            DefinitionError::DuplicateOpcode(opcode) => {
+                write!(f, "操作码 {:02X}H 重复定义", opcode)
+            }
+            DefinitionError::UndefinedEntry(mnemonic, entry) => {
+                write!(f, "{}指令的微程序入口{}未定义", mnemonic, entry)
+            }
+        }
+    }
+}
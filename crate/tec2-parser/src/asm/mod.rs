@@ -0,0 +1,7 @@
+mod assembler;
+mod disassembler;
+mod instruction;
+
+pub use assembler::*;
+pub use disassembler::*;
+pub use instruction::*;
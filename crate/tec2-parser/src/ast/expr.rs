@@ -9,6 +9,7 @@ pub struct Expr {
 #[derive(Debug)]
 pub struct FlagExpr {
     pub flag: Flag,
+    pub span: (usize, usize), // 在表达式中的位置，不含前面的逗号
     pub next: Option<Box<FlagExpr>>,
 }
 
@@ -16,6 +17,17 @@ pub struct FlagExpr {
 pub struct Assignment {
     pub term: Term,
     pub identifier: Identifier,
+    pub term_span: (usize, usize),
+    pub identifier_span: (usize, usize),
+}
+
+/// 连续运算的赋值，如R1 + R2 + R3 -> R4，需要拆成多条微指令
+#[derive(Debug)]
+pub struct Chain {
+    pub first: Primary,
+    pub rest: Vec<(Operator, Primary)>,
+    pub identifier: Identifier,
+    pub flags: (usize, usize), // 后面各个标志在表达式中的位置，含逗号，没有标志时为空
 }
 
 #[derive(Debug)]
@@ -32,10 +44,18 @@ pub enum Primary {
 
 impl Expr {
     pub fn get_flag_vec(&self) -> Vec<&Flag> {
+        self.get_flag_exprs()
+            .into_iter()
+            .map(|flag_expr| &flag_expr.flag)
+            .collect()
+    }
+
+    /// 带位置的各个标志
+    pub fn get_flag_exprs(&self) -> Vec<&FlagExpr> {
         let mut flag_exprs = vec![];
         let mut current = self.flag_expr.as_ref();
         while let Some(flag_expr) = current {
-            flag_exprs.push(&flag_expr.flag);
+            flag_exprs.push(flag_expr.as_ref());
             current = flag_expr.next.as_ref();
         }
         flag_exprs
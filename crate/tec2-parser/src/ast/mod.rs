@@ -1,4 +1,4 @@
-pub mod token;
+#[allow(clippy::module_inception)]
 pub mod ast;
 pub mod expr;
-
+pub mod token;
@@ -1,4 +1,7 @@
-#[derive(Debug, PartialEq)]
+use crate::Error;
+use crate::program::parse_number;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum Identifier {
     PC,    // PC
     AR,    // 地址寄存器
@@ -7,16 +10,17 @@ pub enum Identifier {
     Q,     // Q，ALU输出寄存器
     DR,    // DR寄存器，B寄存器
     IP,    // IP，当前执行指令的地址
+    IR,    // 指令寄存器，只能写入
     R(u8), // 普通寄存器，R0、R1...
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum Operator {
     Add,   // 加法
     Minus, // 减法
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum Condition {
     Zero, // 满足条件
     One,
@@ -32,6 +36,88 @@ pub enum Condition {
     IR108, // 从IR10-8读取数据，比如说你把程序写入到100H中，然后用D5（表中对应是D4）调用，那么SC=5，/CC= Z
 }
 
+impl Condition {
+    /// 条件对应的(SCC, SC)
+    pub fn scc_sc(&self) -> (u8, u8) {
+        match self {
+            Condition::Zero => (0, 0),
+            Condition::One => (1, 0),
+            Condition::NotFS1 => (2, 0),
+            Condition::NotFS2 => (3, 0),
+            Condition::NotFS3 => (4, 0),
+            Condition::NotWait => (5, 0),
+            Condition::NotC => (2, 1),
+            Condition::NotZ => (3, 1),
+            Condition::NotV => (4, 1),
+            Condition::NotS => (5, 1),
+            Condition::NotINT => (6, 1),
+            Condition::IR108 => (7, 0), // 也可以是(7, 1)
+        }
+    }
+
+    pub fn from_scc_sc(scc: u8, sc: u8) -> Option<Self> {
+        match (scc, sc) {
+            (0, 0) => Some(Condition::Zero),
+            (1, 0) => Some(Condition::One),
+            (2, 0) => Some(Condition::NotFS1),
+            (3, 0) => Some(Condition::NotFS2),
+            (4, 0) => Some(Condition::NotFS3),
+            (5, 0) => Some(Condition::NotWait),
+            (2, 1) => Some(Condition::NotC),
+            (3, 1) => Some(Condition::NotZ),
+            (4, 1) => Some(Condition::NotV),
+            (5, 1) => Some(Condition::NotS),
+            (6, 1) => Some(Condition::NotINT),
+            (7, _) => Some(Condition::IR108),
+            _ => None,
+        }
+    }
+}
+
+impl std::fmt::Display for Identifier {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Identifier::PC => f.write_str("PC"),
+            Identifier::AR => f.write_str("AR"),
+            Identifier::MEM => f.write_str("MEM"),
+            Identifier::SR => f.write_str("SR"),
+            Identifier::Q => f.write_str("Q"),
+            Identifier::DR => f.write_str("DR"),
+            Identifier::IP => f.write_str("IP"),
+            Identifier::IR => f.write_str("IR"),
+            Identifier::R(val) => write!(f, "R{}", val),
+        }
+    }
+}
+
+impl std::fmt::Display for Operator {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Operator::Add => "+",
+            Operator::Minus => "-",
+        })
+    }
+}
+
+impl std::fmt::Display for Condition {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Condition::Zero => "0",
+            Condition::One => "1",
+            Condition::NotFS1 => "/FS1",
+            Condition::NotFS2 => "/FS2",
+            Condition::NotFS3 => "/FS3",
+            Condition::NotWait => "/WAIT",
+            Condition::NotS => "/S",
+            Condition::NotV => "/V",
+            Condition::NotZ => "/Z",
+            Condition::NotC => "/C",
+            Condition::NotINT => "/INT",
+            Condition::IR108 => "IR10-8",
+        })
+    }
+}
+
 #[derive(Debug)]
 pub enum Extra {
     CC,
@@ -40,10 +126,28 @@ pub enum Extra {
     EQUAL,
 }
 
+/// 下地址，可以是地址也可以是微程序中的标号
+#[derive(Debug, Clone, PartialEq)]
+pub enum Target {
+    Address(u16),
+    Label(String),
+}
+
+impl Target {
+    /// 按parse_number解析的地址，超出16位时报错而不是截断
+    pub fn address(text: &str) -> Result<Self, Error> {
+        let address = parse_number(text).unwrap_or(u32::MAX);
+        u16::try_from(address)
+            .map(Target::Address)
+            .map_err(|_| Error::AddressOutOfRange(address))
+    }
+}
+
 #[derive(Debug)]
 pub enum Flag {
     Condition(Condition),
     PCStep,
     CarryFromALU,
-    Next(u16),
+    Next(Target),
+    Sequence(String), // CI=JMAP等，指定Am2910的指令
 }
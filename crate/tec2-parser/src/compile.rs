@@ -0,0 +1,173 @@
+use crate::ast::expr::Primary;
+use crate::ast::token::{Identifier, Operator};
+use crate::grammar;
+use crate::result::{Encoding, ParseResult};
+use crate::{CanNotBeAchievedReason, Error};
+use serde::Serialize;
+use std::collections::HashMap;
+
+/// 拆分得到的一条微指令
+#[derive(Debug, Clone, Serialize)]
+pub struct Step {
+    pub source: String,
+    pub encoding: Encoding,
+}
+
+/// 把一个周期完成不了的表达式拆成几条依次执行的微指令，如R1 + R2 + R3 -> R4、MEM -> MEM、DR + MEM -> Q
+///
+/// scratch为允许存放中间结果的Q或通用寄存器，目的是Q或通用寄存器时也用来暂存，依次尝试，取微指令最少的；
+/// 能直接编码时只有一条，NEXT=、CI=等标志放在最后一条，放不下时单独一条
+pub fn compile(input: &str, scratch: &[Identifier]) -> Result<Vec<Step>, ParseResult> {
+    split(input, scratch, &HashMap::new())
+}
+
+pub(crate) fn split(
+    input: &str,
+    scratch: &[Identifier],
+    labels: &HashMap<String, u16>,
+) -> Result<Vec<Step>, ParseResult> {
+    let scratch: &[Identifier] = match scratch.is_empty() {
+        true => &[Identifier::Q],
+        false => scratch,
+    };
+    if let Some(name) = scratch
+        .iter()
+        .find(|name| !matches!(name, Identifier::Q | Identifier::R(_)))
+    {
+        return Err(ParseResult::ExprParseError {
+            error: Error::InvalidScratch(name.to_string()),
+        });
+    }
+    // 没有赋值或者有语法错误时按原来的方式解析
+    let Ok(chain) = grammar::ChainParser::new().parse(input) else {
+        return step(input, labels).map(|step| vec![step]);
+    };
+    // 连续运算不能直接编码
+    let direct = (chain.rest.len() < 2).then(|| step(input, labels));
+    if let Some(Ok(step)) = direct {
+        return Ok(vec![step]);
+    }
+    // 拆分不了时，能按一条微指令解析的给出原来的错误
+    let fail = |direct: Option<Result<Step, ParseResult>>| match direct {
+        Some(result) => result.map(|step| vec![step]),
+        None => Err(ParseResult::ExprParseError {
+            error: Error::CanNotBeAchieved(CanNotBeAchievedReason::UnknownExpr),
+        }),
+    };
+    let mut operands = vec![];
+    let mut operators = vec![];
+    for (operator, primary) in std::iter::once((None, &chain.first)).chain(
+        chain
+            .rest
+            .iter()
+            .map(|(operator, primary)| (Some(*operator), primary)),
+    ) {
+        match primary {
+            Primary::Identifier(identifier) => operands.push(*identifier),
+            Primary::Number(_) => return fail(direct),
+        }
+        operators.extend(operator);
+    }
+    let dest = chain.identifier;
+    if dest == Identifier::IP {
+        return fail(direct);
+    }
+    // PC就是R5，拆分时从R5读出
+    let operands = operands
+        .into_iter()
+        .map(|operand| match operand {
+            Identifier::PC => Identifier::R(5),
+            operand => operand,
+        })
+        .collect::<Vec<_>>();
+    // 目的寄存器最后总会被覆盖，也可以暂存
+    let dest_scratch = matches!(dest, Identifier::Q | Identifier::R(_)).then_some(dest);
+    let plan = dest_scratch
+        .iter()
+        .chain(scratch)
+        .filter_map(|scratch| plan(&operands, &operators, dest, *scratch, labels))
+        .min_by_key(Vec::len);
+    let Some(mut steps) = plan else {
+        return fail(direct);
+    };
+
+    let flags = &input[chain.flags.0..chain.flags.1];
+    if !flags.is_empty() {
+        let last = steps.pop().unwrap();
+        match step(&format!("{}{}", last.source, flags), labels) {
+            Ok(step) => steps.push(step),
+            Err(_) => {
+                steps.push(last);
+                // 前面补上空格，出错时的位置和输入一致
+                let flags = format!("{}{}", " ".repeat(chain.flags.0), flags);
+                steps.push(step(&flags, labels)?);
+            }
+        }
+    }
+    Ok(steps)
+}
+
+/// 用scratch暂存中间结果，从左到右依次运算
+fn plan(
+    operands: &[Identifier],
+    operators: &[Operator],
+    dest: Identifier,
+    scratch: Identifier,
+    labels: &HashMap<String, u16>,
+) -> Option<Vec<Step>> {
+    // 从第i个操作数起还要读取时，scratch不能被覆盖
+    let free = |i: usize| !operands[i..].contains(&scratch);
+    // 把source的结果送到dest，dest不是暂存寄存器时可以先放到暂存寄存器再传送
+    let emit = |steps: &mut Vec<Step>, source: String, dest: Identifier, i: usize| {
+        if (dest != scratch || free(i))
+            && let Ok(step) = step(&format!("{} -> {}", source, dest), labels)
+        {
+            steps.push(step);
+            return Some(());
+        }
+        if dest == scratch || !free(i) {
+            return None;
+        }
+        let first = step(&format!("{} -> {}", source, scratch), labels).ok()?;
+        let second = step(&format!("{} -> {}", scratch, dest), labels).ok()?;
+        steps.extend([first, second]);
+        Some(())
+    };
+    let mut steps = vec![];
+    if operators.is_empty() {
+        emit(&mut steps, operands[0].to_string(), dest, 1)?;
+        return Some(steps);
+    }
+    let target = |i: usize| match i == operators.len() {
+        true => dest,
+        false => scratch,
+    };
+    // 第一次运算先试两个操作数直接运算，不行时先把第一个操作数放到暂存寄存器
+    let first = format!("{} {} {}", operands[0], operators[0], operands[1]);
+    if emit(&mut steps, first, target(1), 2).is_none() {
+        if operands[0] != scratch {
+            if !free(1) {
+                return None;
+            }
+            steps.push(step(&format!("{} -> {}", operands[0], scratch), labels).ok()?);
+        }
+        let first = format!("{} {} {}", scratch, operators[0], operands[1]);
+        emit(&mut steps, first, target(1), 2)?;
+    }
+    for i in 2..=operators.len() {
+        let source = format!("{} {} {}", scratch, operators[i - 1], operands[i]);
+        emit(&mut steps, source, target(i), i + 1)?;
+    }
+    Some(steps)
+}
+
+/// 能在一条微指令中完成的编码
+fn step(source: &str, labels: &HashMap<String, u16>) -> Result<Step, ParseResult> {
+    match ParseResult::parse_with_labels(source, labels) {
+        ParseResult::Result(encoding) => Ok(Step {
+            source: source.trim().to_string(),
+            encoding,
+        }),
+        error => Err(error),
+    }
+}
@@ -0,0 +1,119 @@
+use serde::Serialize;
+use std::fmt::{Display, Formatter};
+
+/// 诊断信息的严重程度
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+    Error,   // 无法编码
+    Warning, // 可以编码，但很可能不是想要的结果
+    Note,    // 可以编码，给出写法上的提示
+}
+
+/// 表达式可以编码，但值得怀疑的地方
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", content = "detail", rename_all = "snake_case")]
+pub enum Lint {
+    ConditionFromIr, // CC#=S、V、Z、C实际按IR10-8选择条件
+    IrNotLoaded,     // 同一条微指令中MEM -> IR，IR10-8还是上一条指令的
+    CarryWithoutAlu, // CarryFromALU，但没有经过ALU的赋值
+    CarryFromMove,   // CarryFromALU，但只是传送数据
+    RegisterAsPc,    // R5 -> AR，MI8-6为NONE，取指时通常要FBA
+}
+
+/// 解析时给出的一条诊断信息，span为在表达式中的位置
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub message: String,
+    pub span: (usize, usize),
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub lint: Option<Lint>,
+}
+
+impl Lint {
+    pub fn severity(&self) -> Severity {
+        match self {
+            Lint::IrNotLoaded | Lint::CarryWithoutAlu => Severity::Warning,
+            Lint::ConditionFromIr | Lint::CarryFromMove | Lint::RegisterAsPc => Severity::Note,
+        }
+    }
+
+    /// 问题在表达式中的位置
+    pub fn span(&self, input: &str) -> (usize, usize) {
+        match self {
+            Lint::ConditionFromIr | Lint::IrNotLoaded => keyword_span(input, "CC#"),
+            Lint::CarryWithoutAlu | Lint::CarryFromMove => keyword_span(input, "CARRYFROMALU"),
+            Lint::RegisterAsPc => assignment_span(input),
+        }
+    }
+
+    pub fn diagnostic(&self, input: &str) -> Diagnostic {
+        Diagnostic {
+            severity: self.severity(),
+            message: self.to_string(),
+            span: self.span(input),
+            lint: Some(self.clone()),
+        }
+    }
+}
+
+impl Display for Severity {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Severity::Error => f.write_str("错误"),
+            Severity::Warning => f.write_str("警告"),
+            Severity::Note => f.write_str("提示"),
+        }
+    }
+}
+
+impl Display for Lint {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Lint::ConditionFromIr => f.write_str(
+                "CC#=S、V、Z、C按IR10-8选择标志位(SCC=7)，不是直接测试该标志位，直接测试应写CC#=/S、/V、/Z、/C",
+            ),
+            Lint::IrNotLoaded => f.write_str(
+                "MEM -> IR在这条微指令结束时才装入IR，CC#按IR10-8测试的还是上一条指令，应在下一条微指令中测试",
+            ),
+            Lint::CarryWithoutAlu => {
+                f.write_str("没有经过ALU的赋值，CarryFromALU保存的标志位没有意义")
+            }
+            Lint::CarryFromMove => {
+                f.write_str("只是传送数据(0加操作数)，CarryFromALU保存的C和V总是0")
+            }
+            Lint::RegisterAsPc => {
+                f.write_str("R5就是PC，取指写PC -> AR才能和PC + 1 -> PC放在同一条微指令中")
+            }
+        }
+    }
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.severity, self.message)
+    }
+}
+
+/// 第一个逗号之前的赋值，没有赋值时为整个表达式
+pub(crate) fn assignment_span(source: &str) -> (usize, usize) {
+    match source.find(',') {
+        Some(0) | None => (0, source.len()),
+        Some(end) => (0, source[..end].trim_end().len()),
+    }
+}
+
+/// 从keyword开始到下一个逗号，没有时为整个表达式
+pub(crate) fn keyword_span(source: &str, keyword: &str) -> (usize, usize) {
+    let upper = source.to_ascii_uppercase();
+    match upper.find(keyword) {
+        Some(start) => {
+            let end = source[start..]
+                .find(',')
+                .map_or(source.len(), |end| start + end);
+            (start, source[..end].trim_end().len())
+        }
+        None => (0, source.len()),
+    }
+}
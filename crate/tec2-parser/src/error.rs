@@ -1,19 +1,58 @@
+use serde::Serialize;
 use std::fmt::Formatter;
 
+#[derive(Serialize)]
+#[serde(tag = "kind", content = "detail", rename_all = "snake_case")]
 pub enum Error {
     CanNotBeAchieved(CanNotBeAchievedReason),
+    UndefinedLabel(String),   // NEXT使用了未定义的标号
+    AddressOutOfRange(u32),   // 超出了下地址字段的位数
+    InvalidCondition(String), // CC#=后面不是0、1或标志位
+    InvalidHex(String),       // 无法解码的微指令
+    InvalidFieldValue {
+        field: &'static str,
+        value: u64,
+    },
+    UnknownSequence(String), // CI=后面不是Am2910的指令
+    FieldDoesNotFit {
+        field: &'static str,
+        value: u16,
+    }, // 当前微指令格式放不下
+    InvalidScratch(String),  // 拆分表达式时只能用Q和通用寄存器暂存
+    // 同一字段被设置了不同的值，span为后设置的子表达式的位置
+    FieldConflict {
+        field: &'static str,
+        kept: u16,
+        ignored: u16,
+        span: (usize, usize),
+    },
 }
 
+#[derive(Serialize)]
 pub enum CanNotBeAchievedReason {
     SACanNotBeWrite,
     LeftRightCanNotBeSame,
     ARCanNotBeRead, // 存疑
+    IRCanNotBeRead,
     DRCanNotInBinaryWithD,
+    PCCanNotInBinary,   // PC + 1 -> PC以外的PC运算
+    NumberCanNotBeRead, // 没有立即数通路
+    IPCanNotBeWrite,
     UnknownExpr,
 }
 
 pub type Result = std::result::Result<(), Error>;
 
+impl Error {
+    /// 能确定出错的子表达式时为它的位置
+    pub fn span(&self) -> Option<(usize, usize)> {
+        match self {
+            Error::FieldConflict { span, .. } => Some(*span),
+            _ => None,
+        }
+    }
+}
+
 impl std::fmt::Display for Error {
     fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
         match self {
@@ -21,6 +60,38 @@ impl std::fmt::Display for Error {
                 f.write_str("代码无法实现:")?;
                 reason.fmt(f)
             }
+            Error::UndefinedLabel(label) => write!(f, "未定义的标号: {}", label),
+            Error::AddressOutOfRange(address) => {
+                write!(f, "地址超出范围: {:X}H", address)
+            }
+            Error::InvalidCondition(condition) => {
+                write!(
+                    f,
+                    "CC#只能是0、1、S、V、Z、C或/S、/V、/Z、/C: {}",
+                    condition
+                )
+            }
+            Error::InvalidHex(hex) => write!(f, "不是合法的十六进制微指令: {}", hex),
+            Error::InvalidFieldValue { field, value } => {
+                write!(f, "{}字段没有定义值{}", field, value)
+            }
+            Error::UnknownSequence(name) => write!(f, "未知的微程序控制器指令: {}", name),
+            Error::FieldDoesNotFit { field, value } => {
+                write!(f, "当前的微指令格式放不下{}字段的值{}", field, value)
+            }
+            Error::InvalidScratch(name) => {
+                write!(f, "只能用Q或通用寄存器作为暂存寄存器: {}", name)
+            }
+            Error::FieldConflict {
+                field,
+                kept,
+                ignored,
+                ..
+            } => write!(
+                f,
+                "{}字段同时需要{}和{}，这条微指令无法同时完成这些操作",
+                field, kept, ignored
+            ),
         }
     }
 }
@@ -33,10 +104,18 @@ impl std::fmt::Display for CanNotBeAchievedReason {
             }
             CanNotBeAchievedReason::LeftRightCanNotBeSame => f.write_str("表达式左右两侧不能相等"),
             CanNotBeAchievedReason::ARCanNotBeRead => f.write_str("AR不能读取"),
+            CanNotBeAchievedReason::IRCanNotBeRead => f.write_str("IR不能读取"),
             CanNotBeAchievedReason::UnknownExpr => f.write_str("未知的表达式"),
             CanNotBeAchievedReason::DRCanNotInBinaryWithD => {
                 f.write_str("DR不能和D(MEM)组成二元表达式")
             }
+            CanNotBeAchievedReason::PCCanNotInBinary => {
+                f.write_str("PC不能直接参与运算，可以用R5代替")
+            }
+            CanNotBeAchievedReason::NumberCanNotBeRead => {
+                f.write_str("数字不能作为操作数，数据通路中没有立即数")
+            }
+            CanNotBeAchievedReason::IPCanNotBeWrite => f.write_str("IP不能被写入"),
         }
     }
 }
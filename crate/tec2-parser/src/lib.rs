@@ -1,9 +1,18 @@
+extern crate self as tec2_parser;
+
 use lalrpop_util::lalrpop_mod;
+pub mod asm;
 pub mod ast;
+pub mod compile;
 mod custom_macro;
+pub mod diagnostic;
 pub mod error;
 pub mod parser;
+pub mod program;
+pub mod result;
+pub mod sim;
 
+pub use diagnostic::{Diagnostic, Lint, Severity};
 pub use error::{CanNotBeAchievedReason, Error, Result};
 
 lalrpop_mod!(pub grammar);
@@ -0,0 +1,147 @@
+use crate::Error;
+use crate::ast::token::Condition;
+use crate::parser::format::{Padding, WordFormat, WordOrder};
+use crate::parser::instrument::{Ci, DC1, DC2, Instrument, MEM, Mi20, Mi53, Mi86, SCi, SSH, SST};
+use crate::parser::layout::Layout;
+use std::sync::Arc;
+
+/// 按当前微指令格式把微指令还原为各个字段
+#[derive(Debug, Clone)]
+pub struct Decoder {
+    layout: Arc<Layout>,
+    hex: Vec<u8>,
+}
+
+impl Decoder {
+    /// 按当前使用的微指令格式解码，见with_layout
+    pub fn new(hex: &[u8]) -> Self {
+        Self::with_layout(hex, Layout::active())
+    }
+
+    /// hex按高位在前，长度和微指令格式不一致时按低位对齐
+    pub fn with_layout(hex: &[u8], layout: Arc<Layout>) -> Self {
+        let value = hex
+            .iter()
+            .fold(0u64, |value, &byte| (value << 8) | byte as u64);
+        let hex = value.to_be_bytes()[8 - layout.bytes()..].to_vec();
+        Self { layout, hex }
+    }
+
+    /// 支持14位十六进制，或者tec-2.py输出的16位(可带空格)，如"0000 0E00 90B0 008A"
+    ///
+    /// 按当前使用的微指令格式解码，16位字按高位补0、高位字在前，其他排列和格式用from_words
+    pub fn from_hex(input: &str) -> std::result::Result<Self, Error> {
+        Self::from_words(input, &WordFormat::default(), Layout::active())
+    }
+
+    /// 按format还原补齐到16位整数倍的微指令，和WordFormat::words相反，补的0不为0时报错
+    pub fn from_words(
+        input: &str,
+        format: &WordFormat,
+        layout: Arc<Layout>,
+    ) -> std::result::Result<Self, Error> {
+        let invalid = || Error::InvalidHex(input.to_string());
+        let mut digits = input
+            .chars()
+            .filter(|c| !c.is_whitespace() && *c != '_')
+            .collect::<String>();
+        let width = layout.width as u32;
+        let bits = digits.len() as u32 * 4;
+        let padding = bits.saturating_sub(width);
+        // 比微指令长的输入是补齐后的16位字
+        if padding > 0 && digits.len().is_multiple_of(4) && format.order == WordOrder::LowFirst {
+            let chars = digits.chars().collect::<Vec<_>>();
+            digits = chars.chunks(4).rev().flatten().collect();
+        }
+        let mut value = u64::from_str_radix(&digits, 16).map_err(|_| invalid())?;
+        if format.padding == Padding::Right && padding > 0 {
+            if value.trailing_zeros() < padding {
+                return Err(invalid());
+            }
+            value >>= padding;
+        }
+        match value.checked_shr(width).unwrap_or(0) {
+            0 => Ok(Self::with_layout(&value.to_be_bytes(), layout)),
+            _ => Err(invalid()),
+        }
+    }
+
+    pub fn layout(&self) -> &Arc<Layout> {
+        &self.layout
+    }
+
+    pub fn bin(&self) -> Vec<u8> {
+        self.hex
+            .iter()
+            .flat_map(|byte| (0..8).rev().map(move |i| (byte >> i) & 1))
+            .collect()
+    }
+
+    pub fn hex(&self) -> Vec<u8> {
+        self.hex.clone()
+    }
+
+    pub fn instrument(&self, field: &Instrument) -> Instrument {
+        self.layout.decode(&self.hex, field)
+    }
+
+    /// 按位置排列的全部字段
+    pub fn instruments(&self) -> Vec<Instrument> {
+        self.layout
+            .instruments()
+            .iter()
+            .map(|field| self.instrument(field))
+            .collect()
+    }
+
+    fn value(&self, field: Instrument) -> u8 {
+        self.instrument(&field).value() as u8
+    }
+
+    pub fn condition(&self) -> Option<Condition> {
+        Condition::from_scc_sc(
+            self.value(Instrument::SCC([0; 3])),
+            self.value(Instrument::SC([0; 1])),
+        )
+    }
+
+    pub fn mem(&self) -> MEM {
+        MEM::decode(
+            self.value(Instrument::MIO([0; 1])),
+            self.value(Instrument::REQ([0; 1])),
+            self.value(Instrument::WE([0; 1])),
+        )
+    }
+
+    /// 字段的含义
+    pub fn describe(instrument: &Instrument) -> String {
+        let value = instrument.value();
+        let name = |result: Option<String>| result.unwrap_or_else(|| "未知".to_string());
+        match instrument {
+            Instrument::NEXT(_) => format!("{:03X}H", value),
+            Instrument::CI(_) => name(Ci::try_from(value as u64).ok().map(|x| x.to_string())),
+            Instrument::SST(_) => name(SST::try_from(value as u64).ok().map(|x| x.to_string())),
+            Instrument::MI86(_) => name(Mi86::try_from(value as u64).ok().map(|x| x.to_string())),
+            Instrument::MI53(_) => name(Mi53::try_from(value as u64).ok().map(|x| x.to_string())),
+            Instrument::MI20(_) => name(Mi20::try_from(value as u64).ok().map(|x| x.to_string())),
+            Instrument::SCi(_) => name(SCi::try_from(value as u64).ok().map(|x| x.to_string())),
+            Instrument::SSH(_) => name(SSH::try_from(value as u64).ok().map(|x| x.to_string())),
+            Instrument::DC1(_) => name(DC1::try_from(value as u64).ok().map(|x| x.to_string())),
+            Instrument::DC2(_) => name(DC2::try_from(value as u64).ok().map(|x| x.to_string())),
+            Instrument::A(_) | Instrument::B(_) => format!("R{}", value),
+            Instrument::SA(_) => match value {
+                0 => "A口来自A字段".to_string(),
+                _ => "A口来自SR(IR3-0)".to_string(),
+            },
+            Instrument::SB(_) => match value {
+                0 => "B口来自B字段".to_string(),
+                _ => "B口来自DR(IR7-4)".to_string(),
+            },
+            Instrument::SCC(_)
+            | Instrument::SC(_)
+            | Instrument::MIO(_)
+            | Instrument::REQ(_)
+            | Instrument::WE(_) => value.to_string(),
+        }
+    }
+}
@@ -0,0 +1,101 @@
+use std::fmt::{Display, Formatter};
+
+/// 微指令补齐到16位的整数倍时0的填充位置
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Padding {
+    #[default]
+    Left, // 高位补0，和tec-2.py的"{:0>16}"一致
+    Right, // 低位补0
+}
+
+/// 16位字的排列顺序
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum WordOrder {
+    #[default]
+    HighFirst, // 高位字在前，按面板手拨的顺序
+    LowFirst, // 低位字在前
+}
+
+/// 按16位字输出，用于在TEC-2面板上手动拨入微指令
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct WordFormat {
+    pub padding: Padding,
+    pub order: WordOrder,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OutputMode {
+    #[default]
+    Hex, // 十六进制，56位微指令为14位
+    Words(WordFormat),
+}
+
+impl WordFormat {
+    pub const fn new(padding: Padding, order: WordOrder) -> Self {
+        Self { padding, order }
+    }
+
+    pub fn words(&self, hex: &[u8]) -> Vec<u16> {
+        let mut bytes = hex.to_vec();
+        if !bytes.len().is_multiple_of(2) {
+            match self.padding {
+                Padding::Left => bytes.insert(0, 0),
+                Padding::Right => bytes.push(0),
+            }
+        }
+        let mut words = bytes
+            .chunks_exact(2)
+            .map(|word| u16::from_be_bytes([word[0], word[1]]))
+            .collect::<Vec<_>>();
+        if self.order == WordOrder::LowFirst {
+            words.reverse();
+        }
+        words
+    }
+
+    pub fn format(&self, hex: &[u8]) -> String {
+        self.words(hex)
+            .iter()
+            .map(|word| format!("{:04X}", word))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+impl OutputMode {
+    pub fn format(&self, hex: &[u8]) -> String {
+        match self {
+            OutputMode::Hex => hex::encode_upper(hex),
+            OutputMode::Words(format) => format.format(hex),
+        }
+    }
+}
+
+impl Display for Padding {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Padding::Left => f.write_str("高位补0"),
+            Padding::Right => f.write_str("低位补0"),
+        }
+    }
+}
+
+impl Display for WordOrder {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            WordOrder::HighFirst => f.write_str("高位字在前"),
+            WordOrder::LowFirst => f.write_str("低位字在前"),
+        }
+    }
+}
+
+impl Display for OutputMode {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            OutputMode::Hex => f.write_str("十六进制"),
+            OutputMode::Words(format) => {
+                write!(f, "16位字({}, {})", format.padding, format.order)
+            }
+        }
+    }
+}
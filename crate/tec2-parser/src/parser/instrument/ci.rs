@@ -1,17 +1,49 @@
-use crate::parser::instrument::{Instrument, ToInstrument};
-use crate::to_bytes;
+use crate::parser::instrument::ToInstrument;
 use num_enum::{IntoPrimitive, TryFromPrimitive};
 
-#[derive(Debug, Clone, PartialEq, IntoPrimitive, TryFromPrimitive)]
+#[derive(Debug, Clone, PartialEq, IntoPrimitive, TryFromPrimitive, ToInstrument)]
+#[field(CI, width = 4)]
 #[repr(u8)]
 pub enum Ci {
-    INIT = 0, // 初始化
-    IF = 3,   // 条件转移
-    SEQ = 14, // 顺序执行
+    /// 初始化
+    INIT = 0,
+    /// 条件转子程序
+    CJS = 1,
+    /// 按指令映射转移
+    JMAP = 2,
+    /// 条件转移
+    IF = 3,
+    /// 压栈，条件满足时装入计数器
+    PUSH = 4,
+    /// 条件转子程序，不满足时转到计数器
+    JSRP = 5,
+    /// 条件转向量
+    CJV = 6,
+    /// 条件转移，不满足时转到计数器
+    JRP = 7,
+    /// 计数器不为0时重复栈顶循环
+    RFCT = 8,
+    /// 计数器不为0时重复下地址
+    RPCT = 9,
+    /// 条件返回
+    CRTN = 10,
+    /// 条件转移并出栈
+    CJPP = 11,
+    /// 装入计数器并顺序执行
+    LDCT = 12,
+    /// 条件满足时结束循环
+    LOOP = 13,
+    /// 顺序执行
+    SEQ = 14,
+    /// 三路分支
+    TWB = 15,
 }
 
-impl ToInstrument for Ci {
-    fn to_instrument(&self) -> Box<[Instrument]> {
-        Box::new([Instrument::CI(to_bytes!(self.to_owned() as u8, 4))])
+impl Ci {
+    /// 按助记符查找，不区分大小写
+    pub fn from_name(name: &str) -> Option<Ci> {
+        (0..16)
+            .filter_map(|value| Ci::try_from(value as u64).ok())
+            .find(|ci| format!("{:?}", ci).eq_ignore_ascii_case(name))
     }
 }
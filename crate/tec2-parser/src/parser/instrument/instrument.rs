@@ -1,11 +1,7 @@
-use crate::map;
 use crate::parser::instrument::ToInstrument;
-use lazy_static::lazy_static;
-use std::collections::HashMap;
-use std::mem::Discriminant;
-use std::mem::discriminant;
+use serde::Serialize;
 
-#[derive(Eq, PartialEq, Hash, Debug, Clone)]
+#[derive(Eq, PartialEq, Hash, Debug, Clone, Serialize)]
 pub enum Instrument {
     NEXT([u8; 10]),
     CI([u8; 4]),
@@ -28,41 +24,157 @@ pub enum Instrument {
     DC2([u8; 3]),
 }
 
-lazy_static! {
-    static ref E_INSTRUMENTS_MAP: HashMap<Discriminant<Instrument>, (u8, u8)> = map![
-        discriminant(&Instrument::NEXT([0;10])) => (0, 10),
-        discriminant(&Instrument::CI([0;4])) => (12, 4),
-        discriminant(&Instrument::SCC([0;3])) => (16, 3),
-        discriminant(&Instrument::SC([0;1])) => (19, 1),
-        discriminant(&Instrument::SST([0;3])) => (21, 3),
-        discriminant(&Instrument::MIO([0;1])) => (24, 1),
-        discriminant(&Instrument::MI86([0;3])) => (25, 3),
-        discriminant(&Instrument::REQ([0;1])) => (28, 1),
-        discriminant(&Instrument::MI53([0;3])) => (29, 3),
-        discriminant(&Instrument::WE([0;1])) => (32, 1),
-        discriminant(&Instrument::MI20([0;3])) => (33, 3),
-        discriminant(&Instrument::A([0;4])) => (36, 4),
-        discriminant(&Instrument::B([0;4])) => (40, 4),
-        discriminant(&Instrument::SCi([0;2])) => (44, 2),
-        discriminant(&Instrument::SSH([0;2])) => (46, 2),
-        discriminant(&Instrument::SA([0;1])) => (48, 1),
-        discriminant(&Instrument::DC1([0;3])) => (49, 3),
-        discriminant(&Instrument::SB([0;1])) => (52, 1),
-        discriminant(&Instrument::DC2([0;3])) => (53, 3),
-    ];
-}
-
 impl Instrument {
+    /// 字段本身的位数
     pub fn length(&self) -> usize {
-        E_INSTRUMENTS_MAP[&discriminant(self)].1 as usize
+        self.bits().len()
+    }
+
+    /// 按微指令中的位置排列的全部字段，值均为0
+    pub fn fields() -> [Instrument; 19] {
+        [
+            Instrument::NEXT([0; 10]),
+            Instrument::CI([0; 4]),
+            Instrument::SCC([0; 3]),
+            Instrument::SC([0; 1]),
+            Instrument::SST([0; 3]),
+            Instrument::MIO([0; 1]),
+            Instrument::MI86([0; 3]),
+            Instrument::REQ([0; 1]),
+            Instrument::MI53([0; 3]),
+            Instrument::WE([0; 1]),
+            Instrument::MI20([0; 3]),
+            Instrument::A([0; 4]),
+            Instrument::B([0; 4]),
+            Instrument::SCi([0; 2]),
+            Instrument::SSH([0; 2]),
+            Instrument::SA([0; 1]),
+            Instrument::DC1([0; 3]),
+            Instrument::SB([0; 1]),
+            Instrument::DC2([0; 3]),
+        ]
+    }
+
+    /// 字段的标识，用于微指令格式文件
+    pub fn key(&self) -> &'static str {
+        match self {
+            Instrument::NEXT(_) => "NEXT",
+            Instrument::CI(_) => "CI",
+            Instrument::SCC(_) => "SCC",
+            Instrument::SC(_) => "SC",
+            Instrument::SST(_) => "SST",
+            Instrument::MIO(_) => "MIO",
+            Instrument::MI86(_) => "MI86",
+            Instrument::REQ(_) => "REQ",
+            Instrument::MI53(_) => "MI53",
+            Instrument::WE(_) => "WE",
+            Instrument::MI20(_) => "MI20",
+            Instrument::A(_) => "A",
+            Instrument::B(_) => "B",
+            Instrument::SCi(_) => "SCi",
+            Instrument::SSH(_) => "SSH",
+            Instrument::SA(_) => "SA",
+            Instrument::DC1(_) => "DC1",
+            Instrument::SB(_) => "SB",
+            Instrument::DC2(_) => "DC2",
+        }
+    }
+
+    /// 按标识或字段名查找字段，不区分大小写
+    pub fn from_key(key: &str) -> Option<Instrument> {
+        Self::fields().into_iter().find(|field| {
+            field.key().eq_ignore_ascii_case(key) || field.name().eq_ignore_ascii_case(key)
+        })
     }
 
-    pub fn begin(&self) -> u8 {
-        E_INSTRUMENTS_MAP[&discriminant(self)].0
+    /// 字段名，和tec-2.py生成的表头一致
+    pub fn name(&self) -> &'static str {
+        match self {
+            Instrument::NEXT(_) => "下地址",
+            Instrument::CI(_) => "CI",
+            Instrument::SCC(_) => "SCC",
+            Instrument::SC(_) => "SC",
+            Instrument::SST(_) => "SST",
+            Instrument::MIO(_) => "MIO",
+            Instrument::MI86(_) => "MI8-6",
+            Instrument::REQ(_) => "REQ",
+            Instrument::MI53(_) => "MI5-3",
+            Instrument::WE(_) => "WE",
+            Instrument::MI20(_) => "MI2-0",
+            Instrument::A(_) => "A口",
+            Instrument::B(_) => "B口",
+            Instrument::SCi(_) => "SCi",
+            Instrument::SSH(_) => "SSH",
+            Instrument::SA(_) => "SA",
+            Instrument::DC1(_) => "DC1",
+            Instrument::SB(_) => "SB",
+            Instrument::DC2(_) => "DC2",
+        }
     }
 
-    pub fn end(&self) -> u8 {
-        self.begin() + E_INSTRUMENTS_MAP[&discriminant(self)].1
+    pub fn bits(&self) -> &[u8] {
+        match self {
+            Instrument::NEXT(val) => val,
+            Instrument::CI(val) => val,
+            Instrument::SCC(val) => val,
+            Instrument::SC(val) => val,
+            Instrument::SST(val) => val,
+            Instrument::MIO(val) => val,
+            Instrument::MI86(val) => val,
+            Instrument::REQ(val) => val,
+            Instrument::MI53(val) => val,
+            Instrument::WE(val) => val,
+            Instrument::MI20(val) => val,
+            Instrument::A(val) => val,
+            Instrument::B(val) => val,
+            Instrument::SCi(val) => val,
+            Instrument::SSH(val) => val,
+            Instrument::SA(val) => val,
+            Instrument::DC1(val) => val,
+            Instrument::SB(val) => val,
+            Instrument::DC2(val) => val,
+        }
+    }
+
+    fn bits_mut(&mut self) -> &mut [u8] {
+        match self {
+            Instrument::NEXT(val) => val,
+            Instrument::CI(val) => val,
+            Instrument::SCC(val) => val,
+            Instrument::SC(val) => val,
+            Instrument::SST(val) => val,
+            Instrument::MIO(val) => val,
+            Instrument::MI86(val) => val,
+            Instrument::REQ(val) => val,
+            Instrument::MI53(val) => val,
+            Instrument::WE(val) => val,
+            Instrument::MI20(val) => val,
+            Instrument::A(val) => val,
+            Instrument::B(val) => val,
+            Instrument::SCi(val) => val,
+            Instrument::SSH(val) => val,
+            Instrument::SA(val) => val,
+            Instrument::DC1(val) => val,
+            Instrument::SB(val) => val,
+            Instrument::DC2(val) => val,
+        }
+    }
+
+    pub fn value(&self) -> u16 {
+        self.bits()
+            .iter()
+            .fold(0u16, |value, &bit| (value << 1) | (bit & 1) as u16)
+    }
+
+    /// 同类字段，值超出位数时高位被舍去
+    pub fn with_value(&self, value: u16) -> Instrument {
+        let mut instrument = self.to_owned();
+        let bits = instrument.bits_mut();
+        let length = bits.len();
+        for (i, bit) in bits.iter_mut().enumerate() {
+            *bit = (value >> (length - 1 - i)) as u8 & 1;
+        }
+        instrument
     }
 }
 
@@ -71,4 +183,3 @@ impl ToInstrument for Instrument {
         Box::new([self.to_owned()])
     }
 }
-
@@ -1,28 +1,39 @@
 use crate::parser::instrument::{Instrument, ToInstrument};
 use crate::to_bytes;
 use num_enum::{IntoPrimitive, TryFromPrimitive};
+use std::fmt::{Display, Formatter};
 
-#[derive(Debug, Clone, PartialEq, IntoPrimitive, TryFromPrimitive)]
+#[derive(Debug, Clone, PartialEq, IntoPrimitive, TryFromPrimitive, ToInstrument)]
+#[field(MIO, width = 1)]
 #[repr(u8)]
 pub enum MIO {
+    /// 0
     Zero = 0,
+    /// 1
     One = 1,
 }
 
-#[derive(Debug, Clone, PartialEq, IntoPrimitive, TryFromPrimitive)]
+#[derive(Debug, Clone, PartialEq, IntoPrimitive, TryFromPrimitive, ToInstrument)]
+#[field(REQ, width = 1)]
 #[repr(u8)]
 pub enum REQ {
+    /// 0
     Zero = 0,
+    /// 1
     One = 1,
 }
 
-#[derive(Debug, Clone, PartialEq, IntoPrimitive, TryFromPrimitive)]
+#[derive(Debug, Clone, PartialEq, IntoPrimitive, TryFromPrimitive, ToInstrument)]
+#[field(WE, width = 1)]
 #[repr(u8)]
 pub enum WE {
+    /// 0
     Zero = 0,
+    /// 1
     One = 1,
 }
 
+#[derive(Debug, Clone, PartialEq)]
 pub enum MEM {
     MemWrite,
     MemRead,
@@ -32,21 +43,16 @@ pub enum MEM {
     LOAD,
 }
 
-impl ToInstrument for MIO {
-    fn to_instrument(&self) -> Box<[Instrument]> {
-        Box::new([Instrument::MIO(to_bytes!(self.to_owned() as u8, 1))])
-    }
-}
-
-impl ToInstrument for REQ {
-    fn to_instrument(&self) -> Box<[Instrument]> {
-        Box::new([Instrument::REQ(to_bytes!(self.to_owned() as u8, 1))])
-    }
-}
-
-impl ToInstrument for WE {
-    fn to_instrument(&self) -> Box<[Instrument]> {
-        Box::new([Instrument::WE(to_bytes!(self.to_owned() as u8, 1))])
+impl MEM {
+    pub fn decode(mio: u8, req: u8, we: u8) -> Self {
+        match (mio, req, we) {
+            (0, 0, 0) => MEM::MemWrite,
+            (0, 0, 1) => MEM::MemRead,
+            (0, 1, 0) => MEM::IoRead,
+            (0, 1, 1) => MEM::IoWrite,
+            (1, 0, _) => MEM::NONE,
+            _ => MEM::LOAD,
+        }
     }
 }
 
@@ -67,3 +73,16 @@ impl ToInstrument for MEM {
         ])
     }
 }
+
+impl Display for MEM {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MEM::MemWrite => f.write_str("存储器写"),
+            MEM::MemRead => f.write_str("存储器读"),
+            MEM::IoRead => f.write_str("I/O读"),
+            MEM::IoWrite => f.write_str("I/O写"),
+            MEM::NONE => f.write_str("无"),
+            MEM::LOAD => f.write_str("装入"),
+        }
+    }
+}
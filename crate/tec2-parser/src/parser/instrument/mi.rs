@@ -1,57 +1,70 @@
-use crate::parser::instrument::{Instrument, ToInstrument};
-use crate::to_bytes;
+use crate::parser::instrument::ToInstrument;
 use num_enum::{IntoPrimitive, TryFromPrimitive};
 
 /// Mi86就是MI的寄存器选择和Y输出选择
-#[derive(Debug, Clone, PartialEq, IntoPrimitive, TryFromPrimitive)]
+#[derive(Debug, Clone, PartialEq, IntoPrimitive, TryFromPrimitive, ToInstrument)]
+#[field(MI86, width = 3)]
 #[repr(u8)]
 pub enum Mi86 {
-    FQF = 0,      // F->Q F
-    NONE = 1,     // 无 F
-    FBA = 2,      // F->B A
-    FBF = 3,      // F->B F
-    F2BQ2QF = 4,  // F/2->Q F/2->Q F
-    F2BF = 5,     // F/2->B F
-    _2FB2QQF = 6, // 2F->B 2Q->Q F
-    _2FB = 7,     // 2F->B  F
+    /// F->Q, Y=F
+    FQF = 0,
+    /// 无, Y=F
+    NONE = 1,
+    /// F->B, Y=A
+    FBA = 2,
+    /// F->B, Y=F
+    FBF = 3,
+    /// F/2->B, Q/2->Q, Y=F
+    F2BQ2QF = 4,
+    /// F/2->B, Y=F
+    F2BF = 5,
+    /// 2F->B, 2Q->Q, Y=F
+    _2FB2QQF = 6,
+    /// 2F->B, Y=F
+    _2FB = 7,
 }
 
-#[derive(Debug, Clone, PartialEq, IntoPrimitive, TryFromPrimitive)]
+#[derive(Debug, Clone, PartialEq, IntoPrimitive, TryFromPrimitive, ToInstrument)]
+#[field(MI53, width = 3)]
 #[repr(u8)]
 pub enum Mi53 {
+    /// R+S
     RAddS = 0,
+    /// S-R
     SSubR = 1,
+    /// R-S
     RSubS = 2,
+    /// R∨S
+    ROrS = 3,
+    /// R∧S
+    RAndS = 4,
+    /// /R∧S
+    NotRAndS = 5,
+    /// R⊕S
+    RXorS = 6,
+    /// /(R⊕S)
+    RXnorS = 7,
 }
 
 /// 数据的来源，D为MEM读取
-#[derive(Debug, Clone, PartialEq, IntoPrimitive, TryFromPrimitive)]
+#[derive(Debug, Clone, PartialEq, IntoPrimitive, TryFromPrimitive, ToInstrument)]
+#[field(MI20, width = 3)]
 #[repr(u8)]
 pub enum Mi20 {
+    /// R=A, S=Q
     AQ = 0,
+    /// R=A, S=B
     AB = 1,
+    /// R=0, S=Q
     _0Q = 2,
+    /// R=0, S=B
     _0B = 3,
+    /// R=0, S=A
     _0A = 4,
+    /// R=D, S=A
     DA = 5,
+    /// R=D, S=Q
     DQ = 6,
+    /// R=D, S=0
     D0 = 7,
 }
-
-impl ToInstrument for Mi86 {
-    fn to_instrument(&self) -> Box<[Instrument]> {
-        Box::new([Instrument::MI86(to_bytes!(self.to_owned() as u8, 3))])
-    }
-}
-
-impl ToInstrument for Mi53 {
-    fn to_instrument(&self) -> Box<[Instrument]> {
-        Box::new([Instrument::MI53(to_bytes!(self.to_owned() as u8, 3))])
-    }
-}
-
-impl ToInstrument for Mi20 {
-    fn to_instrument(&self) -> Box<[Instrument]> {
-        Box::new([Instrument::MI20(to_bytes!(self.to_owned() as u8, 3))])
-    }
-}
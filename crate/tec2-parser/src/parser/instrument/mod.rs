@@ -1,10 +1,12 @@
 mod ci;
 mod dc;
+#[allow(clippy::module_inception)]
 mod instrument;
 mod ir;
 mod mem;
 mod mi;
 mod sci;
+mod ssh;
 mod sst;
 
 pub use ci::*;
@@ -14,7 +16,9 @@ pub use ir::*;
 pub use mem::*;
 pub use mi::*;
 pub use sci::*;
+pub use ssh::*;
 pub use sst::*;
+pub use tec2_derive::ToInstrument;
 
 pub trait ToInstrument {
     fn to_instrument(&self) -> Box<[Instrument]>;
@@ -1,16 +1,14 @@
-use crate::parser::instrument::{Instrument, ToInstrument};
-use crate::to_bytes;
+use crate::parser::instrument::ToInstrument;
 use num_enum::{IntoPrimitive, TryFromPrimitive};
 
-#[derive(Debug, Clone, PartialEq, IntoPrimitive, TryFromPrimitive)]
+#[derive(Debug, Clone, PartialEq, IntoPrimitive, TryFromPrimitive, ToInstrument)]
+#[field(SCi, width = 2)]
 #[repr(u8)]
 pub enum SCi {
+    /// 最低位进位为0
     None = 0,
+    /// 最低位进位为1
     PCStep = 1,
-}
-
-impl ToInstrument for SCi {
-    fn to_instrument(&self) -> Box<[Instrument]> {
-        Box::new([Instrument::SCi(to_bytes!(self.to_owned() as u8, 2))])
-    }
+    /// 最低位进位为C
+    Carry = 2,
 }
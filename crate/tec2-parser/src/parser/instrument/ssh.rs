@@ -0,0 +1,17 @@
+use crate::parser::instrument::ToInstrument;
+use num_enum::{IntoPrimitive, TryFromPrimitive};
+
+/// 移位时最高位(右移)或最低位(左移)移入的值
+#[derive(Debug, Clone, PartialEq, IntoPrimitive, TryFromPrimitive, ToInstrument)]
+#[field(SSH, width = 2)]
+#[repr(u8)]
+pub enum SSH {
+    /// 移入0
+    Zero = 0,
+    /// 移入C
+    Carry = 1,
+    /// 循环移位
+    Rotate = 2,
+    /// 算术移位，右移时移入符号位
+    Arith = 3,
+}
@@ -0,0 +1,235 @@
+use crate::parser::instrument::Instrument;
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use std::fmt::{Display, Formatter};
+use std::mem::discriminant;
+use std::path::Path;
+use std::sync::{Arc, RwLock};
+
+/// 字段在微指令中的位置，begin从最高位开始数
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FieldLayout {
+    pub field: String, // 字段名，如NEXT、MI86，也可以用MI8-6、下地址
+    pub begin: u8,
+    pub width: u8, // 不能超过字段本身的位数，较窄时值必须放得下
+}
+
+/// 微指令格式，不同的实验箱可以有不同的字段位置和总位数
+///
+/// ```toml
+/// name = "TEC-2"
+/// width = 56
+///
+/// [[fields]]
+/// field = "NEXT"
+/// begin = 0
+/// width = 10
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Layout {
+    pub name: String,
+    pub width: u8, // 总位数，8的倍数，最多64位
+    pub fields: Vec<FieldLayout>,
+}
+
+#[derive(Debug)]
+pub enum LayoutError {
+    Io(String),
+    Syntax(String),
+    InvalidWidth(u8),
+    UnknownField(String),
+    DuplicateField(String),
+    InvalidFieldWidth(String, u8),
+    FieldOutOfRange(String),
+    FieldOverlap(String, String),
+}
+
+lazy_static! {
+    static ref ACTIVE_LAYOUT: RwLock<Arc<Layout>> = RwLock::new(Arc::new(Layout::tec2()));
+}
+
+impl Layout {
+    /// TEC-2的56位微指令
+    pub fn tec2() -> Self {
+        let positions = [
+            0, 12, 16, 19, 21, 24, 25, 28, 29, 32, 33, 36, 40, 44, 46, 48, 49, 52, 53,
+        ];
+        Self {
+            name: "TEC-2".to_string(),
+            width: 56,
+            fields: Instrument::fields()
+                .iter()
+                .zip(positions)
+                .map(|(field, begin)| FieldLayout {
+                    field: field.key().to_string(),
+                    begin,
+                    width: field.length() as u8,
+                })
+                .collect(),
+        }
+    }
+
+    /// 当前使用的微指令格式，默认为TEC-2，是命令行--layout设置的默认值；
+    /// 不指定格式的Decoder::new、ExprParser::new等使用它，需要其他格式时用with_layout显式传入
+    pub fn active() -> Arc<Layout> {
+        ACTIVE_LAYOUT.read().unwrap().clone()
+    }
+
+    /// 切换默认的微指令格式，之后不指定格式的编码和解码都按新格式进行
+    pub fn set_active(layout: Layout) -> Result<(), LayoutError> {
+        layout.validate()?;
+        *ACTIVE_LAYOUT.write().unwrap() = Arc::new(layout);
+        Ok(())
+    }
+
+    pub fn from_toml(input: &str) -> Result<Self, LayoutError> {
+        let layout: Layout =
+            toml::from_str(input).map_err(|error| LayoutError::Syntax(error.to_string()))?;
+        layout.validate()?;
+        Ok(layout)
+    }
+
+    pub fn from_json(input: &str) -> Result<Self, LayoutError> {
+        let layout: Layout =
+            serde_json::from_str(input).map_err(|error| LayoutError::Syntax(error.to_string()))?;
+        layout.validate()?;
+        Ok(layout)
+    }
+
+    /// 按扩展名读取.toml或.json文件
+    pub fn load(path: &Path) -> Result<Self, LayoutError> {
+        let input = std::fs::read_to_string(path)
+            .map_err(|error| LayoutError::Io(format!("{}: {}", path.display(), error)))?;
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some(ext) if ext.eq_ignore_ascii_case("json") => Self::from_json(&input),
+            _ => Self::from_toml(&input),
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), LayoutError> {
+        if self.width == 0 || self.width > 64 || !self.width.is_multiple_of(8) {
+            return Err(LayoutError::InvalidWidth(self.width));
+        }
+        let mut used: Vec<(&FieldLayout, Instrument)> = vec![];
+        for field in self.fields.iter() {
+            let instrument = Instrument::from_key(&field.field)
+                .ok_or_else(|| LayoutError::UnknownField(field.field.clone()))?;
+            if used.iter().any(|(_, x)| x == &instrument) {
+                return Err(LayoutError::DuplicateField(field.field.clone()));
+            }
+            if field.width == 0 || field.width as usize > instrument.length() {
+                return Err(LayoutError::InvalidFieldWidth(
+                    field.field.clone(),
+                    field.width,
+                ));
+            }
+            if field.begin as u16 + field.width as u16 > self.width as u16 {
+                return Err(LayoutError::FieldOutOfRange(field.field.clone()));
+            }
+            if let Some((other, _)) = used.iter().find(|(other, _)| {
+                field.begin < other.begin + other.width && other.begin < field.begin + field.width
+            }) {
+                return Err(LayoutError::FieldOverlap(
+                    other.field.clone(),
+                    field.field.clone(),
+                ));
+            }
+            used.push((field, instrument));
+        }
+        Ok(())
+    }
+
+    /// 微指令的字节数
+    pub fn bytes(&self) -> usize {
+        self.width as usize / 8
+    }
+
+    /// 字段在本格式中的位置，没有该字段时为None
+    pub fn position(&self, instrument: &Instrument) -> Option<&FieldLayout> {
+        self.fields.iter().find(|field| {
+            Instrument::from_key(&field.field)
+                .is_some_and(|x| discriminant(&x) == discriminant(instrument))
+        })
+    }
+
+    /// 本格式中的全部字段，按位置排列，值均为0
+    pub fn instruments(&self) -> Vec<Instrument> {
+        let mut fields = self.fields.iter().collect::<Vec<_>>();
+        fields.sort_by_key(|field| field.begin);
+        fields
+            .into_iter()
+            .filter_map(|field| Instrument::from_key(&field.field))
+            .collect()
+    }
+
+    /// 下地址的最大值
+    pub fn max_address(&self) -> u16 {
+        let width = self
+            .position(&Instrument::NEXT([0; 10]))
+            .map_or(0, |field| field.width);
+        ((1u32 << width) - 1) as u16
+    }
+
+    /// 转移到address的下地址字段，超出下地址的位数时为None
+    pub fn next(&self, address: u16) -> Option<Instrument> {
+        (address <= self.max_address()).then(|| Instrument::NEXT([0; 10]).with_value(address))
+    }
+
+    /// 字段的值是否能放进本格式
+    pub fn fits(&self, instrument: &Instrument) -> bool {
+        match self.position(instrument) {
+            Some(field) => instrument.value() >> field.width == 0,
+            None => instrument.value() == 0,
+        }
+    }
+
+    /// 把各个字段拼成微指令，放不下的高位被舍去
+    pub fn encode<'a>(&self, instruments: impl IntoIterator<Item = &'a Instrument>) -> Vec<u8> {
+        let mut value = 0u64;
+        for instrument in instruments {
+            if let Some(field) = self.position(instrument) {
+                let mask = (1u64 << field.width) - 1;
+                let shift = self.width - field.begin - field.width;
+                value |= (instrument.value() as u64 & mask) << shift;
+            }
+        }
+        value.to_be_bytes()[8 - self.bytes()..].to_vec()
+    }
+
+    /// 从微指令中取出同类字段，本格式中没有的字段为0
+    pub fn decode(&self, hex: &[u8], instrument: &Instrument) -> Instrument {
+        let value = hex
+            .iter()
+            .fold(0u64, |value, &byte| (value << 8) | byte as u64);
+        let value = self.position(instrument).map_or(0, |field| {
+            let shift = self.width - field.begin - field.width;
+            (value >> shift) & ((1u64 << field.width) - 1)
+        });
+        instrument.with_value(value as u16)
+    }
+}
+
+impl Default for Layout {
+    fn default() -> Self {
+        Self::tec2()
+    }
+}
+
+impl Display for LayoutError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LayoutError::Io(message) => write!(f, "无法读取微指令格式: {}", message),
+            LayoutError::Syntax(message) => write!(f, "微指令格式有误: {}", message),
+            LayoutError::InvalidWidth(width) => {
+                write!(f, "微指令总位数必须是8的倍数且不超过64位: {}", width)
+            }
+            LayoutError::UnknownField(field) => write!(f, "未知的字段: {}", field),
+            LayoutError::DuplicateField(field) => write!(f, "字段重复定义: {}", field),
+            LayoutError::InvalidFieldWidth(field, width) => {
+                write!(f, "{}字段的位数{}不合法", field, width)
+            }
+            LayoutError::FieldOutOfRange(field) => write!(f, "{}字段超出了微指令的范围", field),
+            LayoutError::FieldOverlap(a, b) => write!(f, "{}字段和{}字段重叠", a, b),
+        }
+    }
+}
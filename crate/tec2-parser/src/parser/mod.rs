@@ -1,2 +1,6 @@
+pub mod decoder;
+pub mod format;
 pub mod instrument;
+pub mod layout;
+#[allow(clippy::module_inception)]
 pub mod parser;
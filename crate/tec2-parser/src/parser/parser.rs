@@ -1,25 +1,50 @@
 use crate::Error::CanNotBeAchieved;
-use crate::ast::expr::{Assignment, Primary};
+use crate::ast::expr::{Assignment, FlagExpr, Primary};
 use crate::ast::expr::{Expr, Term};
-use crate::ast::token::{Condition, Flag, Identifier, Operator};
+use crate::ast::token::{Condition, Flag, Identifier, Operator, Target};
+use crate::parser::format::WordFormat;
 use crate::parser::instrument::{
     A, B, Ci, DC1, DC2, Instrument, MEM, Mi20, Mi53, Mi86, SCi, SST, ToInstrument,
 };
-use crate::{CanNotBeAchievedReason, Result, to_bytes};
+use crate::parser::layout::Layout;
+use crate::result::FieldState;
+use crate::{CanNotBeAchievedReason, Error, Lint, Result, to_bytes};
 use std::collections::HashMap;
+use std::collections::hash_map::Entry;
 use std::mem::{Discriminant, discriminant};
 use std::rc::Rc;
+use std::sync::Arc;
 
 pub struct ExprParser {
     expr: Rc<Expr>,
     instruments: HashMap<Discriminant<Instrument>, (Instrument, bool)>,
+    labels: HashMap<String, u16>,
+    layout: Arc<Layout>,
+    lints: Vec<Lint>,
+    span: (usize, usize),    // 正在解析的子表达式的位置
+    conflict: Option<Error>, // 第一个字段冲突
 }
 
 impl ExprParser {
     pub fn new(expr: Expr) -> Self {
+        Self::with_labels(expr, HashMap::new())
+    }
+
+    /// labels为微程序中标号对应的地址，用于解析NEXT=标号，按当前使用的微指令格式编码
+    pub fn with_labels(expr: Expr, labels: HashMap<String, u16>) -> Self {
+        Self::with_layout(expr, labels, Layout::active())
+    }
+
+    /// 按layout检查字段和编码
+    pub fn with_layout(expr: Expr, labels: HashMap<String, u16>, layout: Arc<Layout>) -> Self {
         let mut obj = Self {
             expr: Rc::new(expr),
             instruments: HashMap::new(),
+            labels,
+            layout,
+            lints: vec![],
+            span: (0, 0),
+            conflict: None,
         };
         let mut func = |instrument: Box<dyn ToInstrument>| {
             let instruments = instrument.to_instrument();
@@ -38,72 +63,68 @@ impl ExprParser {
         self.instruments.values().map(|x| &x.0).collect::<Vec<_>>()
     }
 
-    pub fn bin(&self) -> [u8; 56] {
-        let mut result = [0u8; 56];
-
-        self.instruments.values().for_each(|(instrument, _)| {
-            let val: Box<&[u8]> = match instrument {
-                Instrument::NEXT(val) => Box::new(val),
-                Instrument::CI(val) => Box::new(val),
-                Instrument::SCC(val) => Box::new(val),
-                Instrument::SC(val) => Box::new(val),
-                Instrument::SST(val) => Box::new(val),
-                Instrument::MIO(val) => Box::new(val),
-                Instrument::MI86(val) => Box::new(val),
-                Instrument::REQ(val) => Box::new(val),
-                Instrument::MI53(val) => Box::new(val),
-                Instrument::WE(val) => Box::new(val),
-                Instrument::MI20(val) => Box::new(val),
-                Instrument::A(val) => Box::new(val),
-                Instrument::B(val) => Box::new(val),
-                Instrument::SCi(val) => Box::new(val),
-                Instrument::SSH(val) => Box::new(val),
-                Instrument::SA(val) => Box::new(val),
-                Instrument::DC1(val) => Box::new(val),
-                Instrument::SB(val) => Box::new(val),
-                Instrument::DC2(val) => Box::new(val),
-            };
-            for i in 0..instrument.length() {
-                result[i + instrument.begin() as usize] = val[i];
-            }
-        });
-        result
+    /// 解析中发现的可疑之处，不影响编码
+    pub fn lints(&self) -> &[Lint] {
+        &self.lints
     }
 
-    pub fn hex(&self) -> [u8; 7] {
-        let mut result = [0u8; 7];
-        let bin = self.bin();
-        for (i, bits_chunk) in bin.chunks_exact(8).enumerate() {
-            let mut byte = 0u8;
-            for (j, &bit) in bits_chunk.iter().enumerate() {
-                let valid_bit = bit & 1;
-                byte |= valid_bit << (7 - j);
-            }
-            result[i] = byte;
+    pub fn field_state(&self, field: &Instrument) -> FieldState {
+        match self.instruments.get(&discriminant(field)) {
+            Some((_, false)) => FieldState::Set,
+            Some((_, true)) => FieldState::Default,
+            None => FieldState::DontCare,
         }
-        result
+    }
+
+    /// 按当前微指令格式排列的各位
+    pub fn bin(&self) -> Vec<u8> {
+        self.hex()
+            .iter()
+            .flat_map(|byte| (0..8).rev().map(move |i| (byte >> i) & 1))
+            .collect()
+    }
+
+    pub fn layout(&self) -> &Arc<Layout> {
+        &self.layout
+    }
+
+    /// 按微指令格式编码
+    pub fn hex(&self) -> Vec<u8> {
+        self.layout
+            .encode(self.instruments.values().map(|(instrument, _)| instrument))
+    }
+
+    pub fn words(&self, format: &WordFormat) -> Vec<u16> {
+        format.words(&self.hex())
     }
 
     fn push_instrument_with_check(&mut self, instrument: Box<dyn ToInstrument>, check: bool) {
         let instruments = instrument.to_instrument();
         instruments.into_iter().for_each(|instrument| {
-            let discriminant = discriminant(&instrument);
-            if self.instruments.contains_key(&discriminant) {
-                let (origin_instrument, origin_check) =
-                    self.instruments.get_mut(&discriminant).unwrap();
-                match origin_check {
-                    &mut true => {
-                        *origin_check = check;
-                        *origin_instrument = instrument
-                    }
-                    &mut false => {
-                        if origin_instrument != &instrument {
-                            println!("添加命令失败: {:?} 被反复添加", instrument)
+            match self.instruments.entry(discriminant(&instrument)) {
+                Entry::Occupied(mut entry) => {
+                    let (origin_instrument, origin_check) = entry.get_mut();
+                    match origin_check {
+                        true => {
+                            *origin_check = check;
+                            *origin_instrument = instrument
+                        }
+                        false => {
+                            if !check && origin_instrument != &instrument && self.conflict.is_none()
+                            {
+                                self.conflict = Some(Error::FieldConflict {
+                                    field: instrument.name(),
+                                    kept: origin_instrument.value(),
+                                    ignored: instrument.value(),
+                                    span: self.span,
+                                });
+                            }
                         }
                     }
                 }
-            } else {
-                self.instruments.insert(discriminant, (instrument, check));
+                Entry::Vacant(entry) => {
+                    entry.insert((instrument, check));
+                }
             }
         })
     }
@@ -119,31 +140,96 @@ impl ExprParser {
         if let Some(assignment) = &expr.assignment {
             self.parse_assignment(assignment)?;
         }
-        self.parse_flag_exprs(expr.get_flag_vec())?;
+        self.parse_flag_exprs(expr.get_flag_exprs())?;
+        // 同一字段需要不同的值时这条微指令完成不了
+        if let Some(conflict) = self.conflict.take() {
+            return Err(conflict);
+        }
+        self.check_layout()?;
+        self.lint(&expr);
+        Ok(())
+    }
+
+    /// 能编码但值得怀疑的写法
+    fn lint(&mut self, expr: &Expr) {
+        let flags = expr.get_flag_vec();
+        if flags
+            .iter()
+            .any(|flag| matches!(flag, Flag::Condition(Condition::IR108)))
+        {
+            self.lints.push(
+                match matches!(&expr.assignment, Some(assignment) if assignment.identifier == Identifier::IR)
+                {
+                    true => Lint::IrNotLoaded,
+                    false => Lint::ConditionFromIr,
+                },
+            );
+        }
+        if flags.iter().any(|flag| matches!(flag, Flag::CarryFromALU)) {
+            match &expr.assignment {
+                None => self.lints.push(Lint::CarryWithoutAlu),
+                Some(assignment) if assignment.term.right.is_none() => {
+                    self.lints.push(Lint::CarryFromMove)
+                }
+                Some(_) => {}
+            }
+        }
+        // 取指写成R5 -> AR时MI8-6为NONE，不能再和PC + 1 -> PC写在一起
+        if let Some(Assignment {
+            term:
+                Term {
+                    left: Primary::Identifier(Identifier::R(5)),
+                    right: None,
+                },
+            identifier: Identifier::AR,
+            ..
+        }) = &expr.assignment
+            && self.field_state(&Mi86::NONE.to_instrument()[0]) == FieldState::Default
+        {
+            self.lints.push(Lint::RegisterAsPc);
+        }
+    }
+
+    /// 指定的字段必须能放进微指令格式
+    fn check_layout(&self) -> Result {
+        match self
+            .instruments
+            .values()
+            .find(|(instrument, default)| !default && !self.layout.fits(instrument))
+        {
+            Some((instrument, _)) => Err(Error::FieldDoesNotFit {
+                field: instrument.name(),
+                value: instrument.value(),
+            }),
+            None => Ok(()),
+        }
+    }
+
+    /// 赋值的目的
+    fn destination(&self) -> Option<Identifier> {
+        self.expr.assignment.as_ref().map(|x| x.identifier)
+    }
+
+    fn parse_flag_exprs(&mut self, flags: Vec<&FlagExpr>) -> Result {
+        // CI=放在最后，覆盖CC#隐含的条件转移
+        let (sequences, flags): (Vec<_>, Vec<_>) = flags
+            .into_iter()
+            .partition(|flag| matches!(flag.flag, Flag::Sequence(_)));
+        for flag in flags.into_iter().chain(sequences) {
+            self.span = flag.span;
+            self.parse_flag_expr(&flag.flag)?;
+        }
         Ok(())
     }
 
-    fn parse_flag_exprs(&mut self, flags: Vec<&Flag>) -> Result {
-        flags.into_iter().for_each(|flag| match flag {
+    fn parse_flag_expr(&mut self, flag: &Flag) -> Result {
+        match flag {
             Flag::Condition(condition) => {
                 self.push_instrument_with_check(
                     Box::new(Instrument::NEXT([0, 0, 1, 0, 1, 0, 0, 1, 0, 0])),
                     true,
                 );
-                let (scc, sc): (u8, u8) = match condition {
-                    Condition::Zero => (0, 0),
-                    Condition::One => (1, 0),
-                    Condition::NotFS1 => (2, 0),
-                    Condition::NotFS2 => (3, 0),
-                    Condition::NotFS3 => (4, 0),
-                    Condition::NotWait => (5, 0),
-                    Condition::NotC => (2, 1),
-                    Condition::NotZ => (3, 1),
-                    Condition::NotV => (4, 1),
-                    Condition::NotS => (5, 1),
-                    Condition::NotINT => (6, 1),
-                    Condition::IR108 => (7, 0), // 也可以是(7, 1)
-                };
+                let (scc, sc) = condition.scc_sc();
                 self.push_instrument(Box::new(Ci::IF));
                 self.push_instrument(Box::new(Instrument::SCC(to_bytes!(scc, 3))));
                 self.push_instrument(Box::new(Instrument::SC(to_bytes!(sc, 1))));
@@ -155,13 +241,35 @@ impl ExprParser {
                 self.push_instrument(Box::new(Mi20::_0B))
             }
             Flag::CarryFromALU => self.push_instrument(Box::new(SST::ALU)),
-            Flag::Next(_) => todo!(),
-        });
+            Flag::Next(target) => {
+                let address = match target {
+                    Target::Address(address) => *address,
+                    Target::Label(label) => *self
+                        .labels
+                        .get(label)
+                        .ok_or_else(|| Error::UndefinedLabel(label.clone()))?,
+                };
+                let next = self
+                    .layout
+                    .next(address)
+                    .ok_or(Error::AddressOutOfRange(address as u32))?;
+                self.push_instrument(Box::new(next));
+            }
+            Flag::Sequence(name) => {
+                let ci = Ci::from_name(name).ok_or_else(|| Error::UnknownSequence(name.clone()))?;
+                self.instruments.insert(
+                    discriminant(&Instrument::CI([0; 4])),
+                    (ci.to_instrument()[0].clone(), false),
+                );
+            }
+        };
         Ok(())
     }
 
     fn parse_assignment(&mut self, assignment: &Assignment) -> Result {
+        self.span = assignment.term_span;
         self.parse_term(&assignment.term)?;
+        self.span = assignment.identifier_span;
         self.parse_assignment_identifier(&assignment.identifier)
     }
 
@@ -185,6 +293,9 @@ impl ExprParser {
                 Identifier::AR => {
                     return Err(CanNotBeAchieved(CanNotBeAchievedReason::ARCanNotBeRead));
                 }
+                Identifier::IR => {
+                    return Err(CanNotBeAchieved(CanNotBeAchievedReason::IRCanNotBeRead));
+                }
                 Identifier::MEM => {
                     self.push_instrument(Box::new(Mi20::D0));
                     self.push_instrument(Box::new(MEM::MemRead))
@@ -204,13 +315,21 @@ impl ExprParser {
                     self.push_instrument(Box::new(B::FromSB(6)));
                     self.push_instrument(Box::new(Mi20::_0B));
                 }
+                // 写入另一个寄存器时B口用于写入，从A口读出
+                Identifier::R(val)
+                    if matches!(self.destination(), Some(Identifier::R(dest)) if dest != *val)
+                        || self.destination() == Some(Identifier::DR) =>
+                {
+                    self.push_instrument(Box::new(A::FromSA(val.to_owned())));
+                    self.push_instrument(Box::new(Mi20::_0A));
+                }
                 Identifier::R(val) => {
                     self.push_instrument(Box::new(B::FromSB(val.to_owned())));
                     self.push_instrument(Box::new(Mi20::_0B));
                 }
             },
             Primary::Number(_) => {
-                todo!("赋值")
+                return Err(CanNotBeAchieved(CanNotBeAchievedReason::NumberCanNotBeRead));
             }
         };
         Ok(())
@@ -223,6 +342,7 @@ impl ExprParser {
                 self.push_instrument(Box::new(Mi86::FBF));
             }
             Identifier::AR => self.push_instrument(Box::new(DC2::AR)),
+            Identifier::IR => self.push_instrument(Box::new(DC2::IR)),
             Identifier::MEM => {
                 self.push_instrument(Box::new(DC1::FromALU));
                 self.push_instrument(Box::new(MEM::MemWrite))
@@ -239,11 +359,12 @@ impl ExprParser {
             }
             Identifier::R(val) => {
                 self.push_instrument(Box::new(Mi86::FBF));
-                self.push_instrument(Box::new(A::FromSA(val.to_owned())));
+                // 写入只用B口，A口可能已经用于读取操作数
+                self.push_instrument_with_check(Box::new(A::FromSA(val.to_owned())), true);
                 self.push_instrument(Box::new(B::FromSB(val.to_owned())))
             }
             Identifier::IP => {
-                todo!()
+                return Err(CanNotBeAchieved(CanNotBeAchievedReason::IPCanNotBeWrite));
             }
         };
         Ok(())
@@ -258,12 +379,32 @@ impl ExprParser {
                         CanNotBeAchievedReason::LeftRightCanNotBeSame,
                     ));
                 }
+                // 两个通用寄存器时结果写回B口，和目的相同的寄存器要放在B口(S)
+                let write_back = register(left).is_some()
+                    && register(right).is_some()
+                    && self.destination() == Some(*left);
+                // Am2901的减法为取反加Cn，最低位进位为1时才是差
+                if *operator == Operator::Minus {
+                    self.push_instrument(Box::new(SCi::PCStep));
+                }
                 match operator {
-                    Operator::Add => self.push_instrument(Box::new(Mi53::RAddS)),
+                    Operator::Add => {
+                        if write_back {
+                            (left, right) = (right, left);
+                        }
+                        self.push_instrument(Box::new(Mi53::RAddS))
+                    }
                     Operator::Minus => match (left, right) {
                         (Identifier::Q, Identifier::SR)
                         | (Identifier::DR, Identifier::SR)
-                        | (_, Identifier::MEM) => {
+                        | (_, Identifier::MEM)
+                        | (Identifier::Q, Identifier::R(_))
+                        | (Identifier::R(_), Identifier::SR)
+                        | (Identifier::DR, Identifier::R(_)) => {
+                            (left, right) = (right, left);
+                            self.push_instrument(Box::new(Mi53::SSubR))
+                        }
+                        _ if write_back => {
                             (left, right) = (right, left);
                             self.push_instrument(Box::new(Mi53::SSubR))
                         }
@@ -272,11 +413,14 @@ impl ExprParser {
                 }
                 match (left, right) {
                     (Identifier::PC, _) | (_, Identifier::PC) => {
-                        todo!()
+                        return Err(CanNotBeAchieved(CanNotBeAchievedReason::PCCanNotInBinary));
                     }
                     (Identifier::AR, _) | (_, Identifier::AR) => {
                         return Err(CanNotBeAchieved(CanNotBeAchievedReason::ARCanNotBeRead));
                     }
+                    (Identifier::IR, _) | (_, Identifier::IR) => {
+                        return Err(CanNotBeAchieved(CanNotBeAchievedReason::IRCanNotBeRead));
+                    }
                     (Identifier::SR, Identifier::Q) | (Identifier::Q, Identifier::SR) => {
                         self.push_instrument(Box::new(A::SR));
                         self.push_instrument(Box::new(Mi20::AQ));
@@ -320,11 +464,46 @@ impl ExprParser {
                         self.push_instrument(Box::new(B::FromSB(5)));
                         self.push_instrument(Box::new(Mi20::DA));
                     }
+                    // 通用寄存器从A口读出，另一个操作数按原来的方式
+                    (Identifier::R(val), Identifier::Q) | (Identifier::Q, Identifier::R(val)) => {
+                        self.push_instrument(Box::new(A::FromSA(val.to_owned())));
+                        self.push_instrument(Box::new(Mi20::AQ));
+                    }
+                    (Identifier::R(val), Identifier::SR) | (Identifier::SR, Identifier::R(val)) => {
+                        self.push_instrument(Box::new(A::SR));
+                        self.push_instrument(Box::new(B::FromSB(val.to_owned())));
+                        self.push_instrument(Box::new(Mi20::AB));
+                    }
+                    (Identifier::R(val), Identifier::DR) | (Identifier::DR, Identifier::R(val)) => {
+                        self.push_instrument(Box::new(A::FromSA(val.to_owned())));
+                        self.push_instrument(Box::new(B::DR));
+                        self.push_instrument(Box::new(Mi20::AB));
+                    }
+                    (Identifier::R(val), Identifier::MEM)
+                    | (Identifier::MEM, Identifier::R(val)) => {
+                        self.push_instrument(Box::new(MEM::MemRead));
+                        self.push_instrument(Box::new(A::FromSA(val.to_owned())));
+                        self.push_instrument(Box::new(Mi20::DA));
+                    }
+                    (left, right) if register(left).is_some() && register(right).is_some() => {
+                        self.push_instrument(Box::new(A::FromSA(register(left).unwrap())));
+                        self.push_instrument(Box::new(B::FromSB(register(right).unwrap())));
+                        self.push_instrument(Box::new(Mi20::AB));
+                    }
                     _ => return Err(CanNotBeAchieved(CanNotBeAchievedReason::UnknownExpr)),
                 }
             }
-            _ => todo!(),
+            _ => return Err(CanNotBeAchieved(CanNotBeAchievedReason::NumberCanNotBeRead)),
         };
         Ok(())
     }
 }
+
+/// 通用寄存器的编号，IP就是R6
+fn register(identifier: &Identifier) -> Option<u8> {
+    match identifier {
+        Identifier::R(val) => Some(*val),
+        Identifier::IP => Some(6),
+        _ => None,
+    }
+}
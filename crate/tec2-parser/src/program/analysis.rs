@@ -0,0 +1,160 @@
+use crate::diagnostic::keyword_span;
+use crate::program::{ControlFlow, EdgeKind, MicroInstruction, Program};
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::{Display, Formatter};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", content = "detail", rename_all = "snake_case")]
+pub enum WarningKind {
+    Unreachable,                // 从入口无法到达
+    EmptyTarget(u16),           // 转移到没有微指令的地址
+    AddressOverlap(u16, usize), // (地址, 另一条微指令的行号)
+    FallThrough(u16),           // 顺序执行到没有微指令的地址
+    MemWithoutAr,               // 访问主存，但前一条微指令没有装入AR
+    PcNotIncremented,           // 取指，但送出PC的微指令没有PC + 1 -> PC
+    PcStepWithoutFetch,         // PC + 1 -> PC，但没有同时把PC送到AR
+    QBeforeWrite,               // 读Q之前没有写过Q
+    ConditionWithoutIr,         // 按IR10-8测试条件，但没有装入指令
+}
+
+/// 静态检查发现的问题，span为在源代码(表达式)中的位置
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Warning {
+    pub line: usize,
+    pub address: u16,
+    pub span: (usize, usize),
+    pub kind: WarningKind,
+}
+
+impl Program {
+    /// 检查无法到达的微指令、转移到空地址、地址重叠和顺序执行到空地址，以及dataflow中的问题
+    ///
+    /// entries为复位和取指等入口，map为JMAP的映射；有JMAP而没有映射时，带标号的微指令都视为入口
+    pub fn analyze(&self, entries: &[u16], map: &BTreeMap<u8, u16>) -> Vec<Warning> {
+        let flow = ControlFlow::new(self, map);
+        let mut warnings = self.dataflow(entries, map);
+        let mut by_address: BTreeMap<u16, Vec<&MicroInstruction>> = BTreeMap::new();
+        for instruction in self.instructions() {
+            by_address
+                .entry(instruction.address)
+                .or_default()
+                .push(instruction);
+        }
+        let warning = |instruction: &MicroInstruction, span, kind| Warning {
+            line: instruction.line,
+            address: instruction.address,
+            span,
+            kind,
+        };
+        let whole = |instruction: &MicroInstruction| (0, instruction.source.len());
+
+        for instructions in by_address.values().filter(|x| x.len() > 1) {
+            for instruction in instructions.iter().skip(1) {
+                let kind = WarningKind::AddressOverlap(instruction.address, instructions[0].line);
+                warnings.push(warning(instruction, whole(instruction), kind));
+            }
+        }
+
+        for edge in flow.edges.iter() {
+            let Some(to) = edge.to else {
+                continue;
+            };
+            if by_address.contains_key(&to) {
+                continue;
+            }
+            for instruction in by_address[&edge.from].iter() {
+                let (span, kind) = match edge.kind {
+                    EdgeKind::Sequential => (whole(instruction), WarningKind::FallThrough(to)),
+                    EdgeKind::Dispatch if edge.label.as_deref() != Some("IR10-8") => {
+                        (whole(instruction), WarningKind::EmptyTarget(to))
+                    }
+                    _ => (
+                        keyword_span(&instruction.source, "NEXT"),
+                        WarningKind::EmptyTarget(to),
+                    ),
+                };
+                let warning = warning(instruction, span, kind);
+                if !warnings.contains(&warning) {
+                    warnings.push(warning);
+                }
+            }
+        }
+
+        let mut starts = entries.iter().copied().collect::<BTreeSet<_>>();
+        if flow.edges.iter().any(|edge| edge.to.is_none()) {
+            starts.extend(
+                self.instructions()
+                    .iter()
+                    .filter(|instruction| !instruction.labels.is_empty())
+                    .map(|instruction| instruction.address),
+            );
+        }
+        let mut reachable = BTreeSet::new();
+        let mut stack = starts.into_iter().collect::<Vec<_>>();
+        while let Some(address) = stack.pop() {
+            if !reachable.insert(address) {
+                continue;
+            }
+            stack.extend(
+                flow.edges
+                    .iter()
+                    .filter(|edge| edge.from == address)
+                    .filter_map(|edge| edge.to),
+            );
+        }
+        for instruction in self.instructions() {
+            if !reachable.contains(&instruction.address) {
+                warnings.push(warning(
+                    instruction,
+                    whole(instruction),
+                    WarningKind::Unreachable,
+                ));
+            }
+        }
+        warnings.sort_by_key(|warning| warning.line);
+        warnings
+    }
+}
+
+impl Display for WarningKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            WarningKind::Unreachable => f.write_str("从入口无法到达这条微指令"),
+            WarningKind::EmptyTarget(address) => {
+                write!(f, "转移到 {:03X}H，但该地址没有微指令", address)
+            }
+            WarningKind::AddressOverlap(address, line) => {
+                write!(f, "地址 {:03X}H 和第{}行重叠", address, line)
+            }
+            WarningKind::FallThrough(address) => {
+                write!(
+                    f,
+                    "顺序执行到 {:03X}H，但该地址没有微指令，是否缺少NEXT",
+                    address
+                )
+            }
+            WarningKind::MemWithoutAr => f.write_str(
+                "访问主存使用的是AR，但前一条微指令没有装入AR，应先用类似PC -> AR的微指令送出地址",
+            ),
+            WarningKind::PcNotIncremented => {
+                f.write_str("读取指令，但送出PC的微指令没有PC + 1 -> PC，PC会一直指向同一条指令")
+            }
+            WarningKind::PcStepWithoutFetch => f.write_str(
+                "PC + 1 -> PC通常和PC -> AR写在一起，在送出地址的同时PC加1，单独使用时会跳过一个字",
+            ),
+            WarningKind::QBeforeWrite => {
+                f.write_str("读Q之前没有写过Q，Q中是上一条机器指令留下的值")
+            }
+            WarningKind::ConditionWithoutIr => f.write_str(
+                "CC#=S、V、Z、C测试的是IR10-8选择的标志位，但此前没有用MEM -> IR装入指令",
+            ),
+        }
+    }
+}
+
+impl Display for Warning {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "第{}行: {}", self.line, self.kind)
+    }
+}
@@ -0,0 +1,246 @@
+use crate::ast::token::Condition;
+use crate::parser::decoder::Decoder;
+use crate::parser::instrument::{Ci, Instrument};
+use crate::parser::layout::Layout;
+use crate::program::Program;
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// 微指令之间转移的种类
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EdgeKind {
+    Sequential,  // 顺序执行，包括条件不满足时
+    Jump,        // 无条件转移
+    Conditional, // 条件满足时转移
+    Call,        // 转子程序
+    Loop,        // 计数器不为0时重复
+    Dispatch,    // JMAP按映射ROM转移，或者按IR10-8分支
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Edge {
+    pub from: u16,
+    pub to: Option<u16>, // JMAP没有给出映射时为None
+    pub kind: EdgeKind,
+    pub label: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Node {
+    pub address: u16,
+    pub labels: Vec<String>,
+    pub source: String,
+    pub returns: bool, // CRTN，返回到调用处
+}
+
+/// 微程序的控制流图，节点为微指令，边按CI和条件确定
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ControlFlow {
+    pub nodes: Vec<Node>,
+    pub edges: Vec<Edge>,
+}
+
+impl ControlFlow {
+    /// map为JMAP使用的操作码到微程序入口的映射，可以为空
+    pub fn new(program: &Program, map: &BTreeMap<u8, u16>) -> Self {
+        let max_address = Layout::active().max_address();
+        let mut nodes = vec![];
+        let mut edges = vec![];
+        let mut instructions = program.instructions().iter().collect::<Vec<_>>();
+        instructions.sort_by_key(|instruction| instruction.address);
+        for instruction in instructions {
+            let decoder = Decoder::new(&instruction.encoding.hex);
+            let from = instruction.address;
+            let field = |instrument: Instrument| decoder.instrument(&instrument).value();
+            let next = field(Instrument::NEXT([0; 10]));
+            let upc = from.wrapping_add(1) & max_address;
+            let ci = Ci::try_from(field(Instrument::CI([0; 4])) as u64).unwrap_or(Ci::SEQ);
+            let condition = decoder.condition();
+            let mut edge = |to: Option<u16>, kind, label: Option<String>| {
+                edges.push(Edge {
+                    from,
+                    to,
+                    kind,
+                    label,
+                })
+            };
+            // 条件为0时总是满足，为1时总是不满足
+            let (always, never) = match condition {
+                Some(Condition::Zero) => (true, false),
+                Some(Condition::One) => (false, true),
+                _ => (false, false),
+            };
+            let branch = |condition: &Option<Condition>| match condition {
+                Some(Condition::IR108) => (EdgeKind::Dispatch, Some("IR10-8".to_string())),
+                Some(condition) => (EdgeKind::Conditional, Some(format!("CC#={}", condition))),
+                None => (EdgeKind::Conditional, None),
+            };
+            match ci {
+                Ci::INIT => edge(Some(0), EdgeKind::Jump, Some("INIT".to_string())),
+                Ci::JMAP => {
+                    if map.is_empty() {
+                        edge(None, EdgeKind::Dispatch, Some("JMAP".to_string()));
+                    }
+                    for (opcode, target) in map {
+                        edge(
+                            Some(*target),
+                            EdgeKind::Dispatch,
+                            Some(format!("{:02X}H", opcode)),
+                        );
+                    }
+                }
+                Ci::CJS | Ci::JSRP if !never => {
+                    edge(Some(next), EdgeKind::Call, Some("CALL".to_string()));
+                    edge(Some(upc), EdgeKind::Sequential, None);
+                }
+                Ci::IF | Ci::CJV | Ci::JRP | Ci::CJPP | Ci::TWB if always => {
+                    edge(Some(next), EdgeKind::Jump, None)
+                }
+                Ci::IF | Ci::CJV | Ci::JRP | Ci::CJPP | Ci::TWB if !never => {
+                    let (kind, label) = branch(&condition);
+                    edge(Some(next), kind, label);
+                    edge(Some(upc), EdgeKind::Sequential, None);
+                }
+                Ci::RPCT => {
+                    edge(Some(next), EdgeKind::Loop, Some("RPCT".to_string()));
+                    edge(Some(upc), EdgeKind::Sequential, None);
+                }
+                Ci::CRTN if always => {}
+                _ => edge(Some(upc), EdgeKind::Sequential, None),
+            }
+            nodes.push(Node {
+                address: from,
+                labels: instruction.labels.clone(),
+                source: instruction.source.clone(),
+                returns: ci == Ci::CRTN,
+            });
+        }
+        Self { nodes, edges }
+    }
+
+    /// 转移目标中没有微指令的地址
+    fn missing(&self) -> BTreeSet<u16> {
+        self.edges
+            .iter()
+            .filter_map(|edge| edge.to)
+            .filter(|to| !self.nodes.iter().any(|node| node.address == *to))
+            .collect()
+    }
+
+    /// Graphviz的DOT格式
+    pub fn to_dot(&self) -> String {
+        let escape = |text: &str| text.replace('\\', "\\\\").replace('"', "\\\"");
+        let mut output =
+            String::from("digraph microprogram {\n    node [shape=box, fontname=\"monospace\"];\n");
+        for node in self.nodes.iter() {
+            let shape = match node.returns {
+                true => ", peripheries=2",
+                false => "",
+            };
+            output.push_str(&format!(
+                "    {} [label=\"{}\"{}];\n",
+                id(Some(node.address)),
+                escape(&node_text(node, "\n")).replace('\n', "\\n"),
+                shape
+            ));
+        }
+        for address in self.missing() {
+            output.push_str(&format!(
+                "    {} [label=\"{:03X}H (空)\", style=dashed];\n",
+                id(Some(address)),
+                address
+            ));
+        }
+        if self.edges.iter().any(|edge| edge.to.is_none()) {
+            output.push_str(&format!(
+                "    {} [label=\"映射ROM\", shape=ellipse];\n",
+                id(None)
+            ));
+        }
+        for edge in self.edges.iter() {
+            let mut attributes = vec![];
+            if let Some(label) = &edge.label {
+                attributes.push(format!("label=\"{}\"", escape(label)));
+            }
+            match edge.kind {
+                EdgeKind::Sequential => {}
+                EdgeKind::Jump => attributes.push("style=bold".to_string()),
+                EdgeKind::Conditional => attributes.push("color=blue".to_string()),
+                EdgeKind::Call => attributes.push("style=dashed".to_string()),
+                EdgeKind::Loop => attributes.push("style=dotted".to_string()),
+                EdgeKind::Dispatch => attributes.push("color=red, style=bold".to_string()),
+            }
+            let attributes = match attributes.is_empty() {
+                true => String::new(),
+                false => format!(" [{}]", attributes.join(", ")),
+            };
+            output.push_str(&format!(
+                "    {} -> {}{};\n",
+                id(Some(edge.from)),
+                id(edge.to),
+                attributes
+            ));
+        }
+        output.push_str("}\n");
+        output
+    }
+
+    /// Mermaid的flowchart
+    pub fn to_mermaid(&self) -> String {
+        let escape = |text: &str| text.replace('"', "#quot;");
+        let mut output = String::from("flowchart TD\n");
+        for node in self.nodes.iter() {
+            let text = escape(&node_text(node, "<br/>"));
+            output.push_str(&match node.returns {
+                true => format!("    {}([\"{}\"])\n", id(Some(node.address)), text),
+                false => format!("    {}[\"{}\"]\n", id(Some(node.address)), text),
+            });
+        }
+        for address in self.missing() {
+            output.push_str(&format!(
+                "    {}[\"{:03X}H (空)\"]\n",
+                id(Some(address)),
+                address
+            ));
+        }
+        if self.edges.iter().any(|edge| edge.to.is_none()) {
+            output.push_str(&format!("    {}((\"映射ROM\"))\n", id(None)));
+        }
+        for edge in self.edges.iter() {
+            let arrow = match edge.kind {
+                EdgeKind::Sequential | EdgeKind::Conditional => "-->",
+                EdgeKind::Jump | EdgeKind::Dispatch => "==>",
+                EdgeKind::Call | EdgeKind::Loop => "-.->",
+            };
+            let label = edge
+                .label
+                .as_ref()
+                .map_or(String::new(), |label| format!("|\"{}\"|", escape(label)));
+            output.push_str(&format!(
+                "    {} {}{} {}\n",
+                id(Some(edge.from)),
+                arrow,
+                label,
+                id(edge.to)
+            ));
+        }
+        output
+    }
+}
+
+fn id(address: Option<u16>) -> String {
+    match address {
+        Some(address) => format!("n{:03X}", address),
+        None => "jmap".to_string(),
+    }
+}
+
+/// 地址和标号一行，源代码一行
+fn node_text(node: &Node, newline: &str) -> String {
+    let mut title = format!("{:03X}H", node.address);
+    for label in node.labels.iter() {
+        title.push_str(&format!(" {}:", label));
+    }
+    format!("{}{}{}", title, newline, node.source)
+}
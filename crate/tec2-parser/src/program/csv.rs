@@ -0,0 +1,165 @@
+use crate::parser::instrument::Instrument;
+use crate::parser::layout::Layout;
+use crate::program::{ImageError, Program};
+use std::collections::BTreeMap;
+
+/// 地址、标号和源代码列，导入时地址列可以省略，其余两列被忽略
+const ADDRESS: &str = "地址";
+const LABEL: &str = "标号";
+const SOURCE: &str = "源代码";
+
+impl Program {
+    /// 每行一条微指令，每个字段一列，值为二进制，和tec-2.py的表格一致
+    pub fn to_csv(&self) -> String {
+        let layout = Layout::active();
+        let instruments = layout.instruments();
+        let mut header = vec![ADDRESS, LABEL];
+        header.extend(instruments.iter().map(Instrument::name));
+        header.push(SOURCE);
+        let mut output = header.join(",") + "\n";
+        for instruction in self.instructions() {
+            let mut row = vec![
+                format!("{:03X}", instruction.address),
+                instruction.labels.join(" "),
+            ];
+            for instrument in instruments.iter() {
+                let field = instruction
+                    .encoding
+                    .fields
+                    .iter()
+                    .find(|field| field.name == instrument.name());
+                row.push(field.map_or(String::new(), |field| field.bits.clone()));
+            }
+            row.push(instruction.source.clone());
+            let row = row.iter().map(|cell| quote(cell)).collect::<Vec<_>>();
+            output.push_str(&row.join(","));
+            output.push('\n');
+        }
+        output
+    }
+}
+
+/// 读取每个字段一列的CSV，字段按列名对应，值为二进制，也可以写为0x前缀或H后缀的十六进制
+///
+/// 没有地址列时从0开始依次排列，空的单元格为0，不认识的列被忽略
+pub(crate) fn from_csv(text: &str, layout: &Layout) -> Result<BTreeMap<u16, Vec<u8>>, ImageError> {
+    let text = text.trim_start_matches('\u{feff}');
+    let mut rows = text
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty());
+    let Some((index, header)) = rows.next() else {
+        return Ok(BTreeMap::new());
+    };
+    let columns = Columns::new(&split(header), layout, index + 1)?;
+    let mut image = BTreeMap::new();
+    let mut address = 0u32;
+    for (index, line) in rows {
+        let (start, hex) = columns.encode(&split(line), layout, index + 1)?;
+        address = start.unwrap_or(address);
+        if address > layout.max_address() as u32 {
+            return Err(ImageError::AddressOutOfRange(address));
+        }
+        image.insert(address as u16, hex);
+        address += 1;
+    }
+    Ok(image)
+}
+
+/// 表头中的地址列和各个字段所在的列
+pub(crate) struct Columns {
+    address: Option<usize>,
+    fields: Vec<(usize, Instrument, u8)>, // (列, 字段, 本格式中的位数)
+}
+
+impl Columns {
+    pub(crate) fn new(header: &[String], layout: &Layout, line: usize) -> Result<Self, ImageError> {
+        let fields = header
+            .iter()
+            .enumerate()
+            .filter_map(|(i, name)| {
+                let instrument = Instrument::from_key(name)?;
+                let width = layout.position(&instrument)?.width;
+                Some((i, instrument, width))
+            })
+            .collect::<Vec<_>>();
+        if fields.is_empty() {
+            return Err(ImageError::InvalidValue(line, header.join(",")));
+        }
+        Ok(Self {
+            address: header.iter().position(|name| name == ADDRESS),
+            fields,
+        })
+    }
+
+    /// 按列取出各个字段拼成微指令，同时返回地址列的值
+    pub(crate) fn encode(
+        &self,
+        cells: &[String],
+        layout: &Layout,
+        line: usize,
+    ) -> Result<(Option<u32>, Vec<u8>), ImageError> {
+        let cell = |i: usize| cells.get(i).map_or("", |cell| cell.trim());
+        let address = self
+            .address
+            .map(|column| {
+                let text = cell(column);
+                u32::from_str_radix(text.strip_suffix(['H', 'h']).unwrap_or(text), 16)
+                    .map_err(|_| ImageError::InvalidValue(line, text.to_string()))
+            })
+            .transpose()?;
+        let mut instruments = vec![];
+        for (i, instrument, width) in self.fields.iter() {
+            let text = cell(*i);
+            let value = parse_cell(text)
+                .filter(|value| value >> width == 0)
+                .ok_or_else(|| {
+                    ImageError::InvalidField(line, instrument.name().to_string(), text.to_string())
+                })?;
+            instruments.push(instrument.with_value(value as u16));
+        }
+        Ok((address, layout.encode(instruments.iter())))
+    }
+}
+
+fn parse_cell(text: &str) -> Option<u32> {
+    if text.is_empty() {
+        return Some(0);
+    }
+    if let Some(hex) = text.strip_prefix("0x").or(text.strip_prefix("0X")) {
+        u32::from_str_radix(hex, 16).ok()
+    } else if let Some(hex) = text.strip_suffix(['H', 'h']) {
+        u32::from_str_radix(hex, 16).ok()
+    } else {
+        u32::from_str_radix(text, 2).ok()
+    }
+}
+
+/// 含有逗号、引号或首尾空格时加上引号
+fn quote(cell: &str) -> String {
+    match cell.contains([',', '"']) || cell.trim() != cell {
+        true => format!("\"{}\"", cell.replace('"', "\"\"")),
+        false => cell.to_string(),
+    }
+}
+
+/// 按逗号拆分一行，引号内的逗号不拆分，""表示一个引号
+fn split(line: &str) -> Vec<String> {
+    let mut cells = vec![];
+    let mut cell = String::new();
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                cell.push('"');
+                chars.next();
+            }
+            '"' => quoted = !quoted,
+            ',' if !quoted => cells.push(std::mem::take(&mut cell)),
+            _ => cell.push(c),
+        }
+    }
+    cells.push(cell);
+    cells.iter().map(|cell| cell.trim().to_string()).collect()
+}
@@ -0,0 +1,162 @@
+use crate::ast::token::Condition;
+use crate::diagnostic::{assignment_span, keyword_span};
+use crate::parser::decoder::Decoder;
+use crate::parser::instrument::{DC2, Instrument, MEM, Mi20, Mi86, SCi};
+use crate::program::{ControlFlow, MicroInstruction, Program, Warning, WarningKind};
+use crate::result::FieldState;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// 数据流分析关心的寄存器状态，都是"所有路径上都已经..."
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct State {
+    q: bool,  // 写过Q
+    ir: bool, // 装入过指令
+}
+
+const UNKNOWN: State = State { q: true, ir: true };
+const RESET: State = State {
+    q: false,
+    ir: false,
+};
+
+/// 一条微指令对各个寄存器的读写
+struct Access {
+    mem: bool,        // 读写主存，使用AR
+    load_ar: bool,    // 写AR
+    ar_from_pc: bool, // PC -> AR
+    load_ir: bool,    // MEM -> IR
+    pc_step: bool,    // PC + 1 -> PC
+    read_q: bool,
+    write_q: bool,
+    ir108: bool, // CC#=S、V、Z、C，条件取自IR10-8
+}
+
+impl Access {
+    fn new(instruction: &MicroInstruction) -> Self {
+        let decoder = Decoder::new(&instruction.encoding.hex);
+        let value = |key| {
+            decoder
+                .instrument(&Instrument::from_key(key).unwrap())
+                .value()
+        };
+        let set = |key| {
+            let instrument = Instrument::from_key(key).unwrap();
+            instruction.encoding.fields.iter().any(|field| {
+                field.instrument.key() == instrument.key() && field.state == FieldState::Set
+            })
+        };
+        let mi20 = Mi20::try_from(value("MI20") as u64).ok();
+        let mi86 = Mi86::try_from(value("MI86") as u64).ok();
+        let mem = decoder.mem();
+        let load_ar = value("DC2") == DC2::AR as u16;
+        let pc_on_b = value("SB") == 0 && value("B") == 5;
+        Self {
+            mem: matches!(mem, MEM::MemRead | MEM::MemWrite),
+            load_ar,
+            ar_from_pc: load_ar && mi20 == Some(Mi20::_0B) && pc_on_b,
+            load_ir: value("DC2") == DC2::IR as u16 && mem == MEM::MemRead,
+            pc_step: value("SCi") == SCi::PCStep as u16 && mi86 == Some(Mi86::FBA) && pc_on_b,
+            read_q: set("MI20") && matches!(mi20, Some(Mi20::AQ | Mi20::_0Q | Mi20::DQ)),
+            write_q: set("MI86")
+                && matches!(mi86, Some(Mi86::FQF | Mi86::F2BQ2QF | Mi86::_2FB2QQF)),
+            ir108: decoder.condition() == Some(Condition::IR108),
+        }
+    }
+}
+
+impl Program {
+    /// 沿控制流检查相邻微指令之间的配合：访问主存前装入AR、取指时PC加1、先写Q再读Q、
+    /// 按IR10-8测试条件前装入指令
+    ///
+    /// entries和map的含义和analyze一样，没有前驱又不是入口的微指令不做假设
+    pub fn dataflow(&self, entries: &[u16], map: &BTreeMap<u8, u16>) -> Vec<Warning> {
+        let flow = ControlFlow::new(self, map);
+        // 地址重叠时只看第一条，重叠由analyze报告
+        let mut words: BTreeMap<u16, (&MicroInstruction, Access)> = BTreeMap::new();
+        for instruction in self.instructions() {
+            words
+                .entry(instruction.address)
+                .or_insert_with(|| (instruction, Access::new(instruction)));
+        }
+        let mut predecessors: BTreeMap<u16, BTreeSet<u16>> = BTreeMap::new();
+        for edge in flow.edges.iter() {
+            if let Some(to) = edge.to.filter(|to| words.contains_key(to)) {
+                predecessors.entry(to).or_default().insert(edge.from);
+            }
+        }
+        let entries = entries.iter().copied().collect::<BTreeSet<_>>();
+
+        // 前向的must分析，从UNKNOWN开始迭代到不动点
+        let mut input = words
+            .keys()
+            .map(|address| (*address, UNKNOWN))
+            .collect::<BTreeMap<_, _>>();
+        let output = |address: u16, state: State, words: &BTreeMap<u16, (_, Access)>| {
+            let access = &words[&address].1;
+            State {
+                q: state.q || access.write_q,
+                ir: state.ir || access.load_ir,
+            }
+        };
+        loop {
+            let mut changed = false;
+            for address in words.keys().copied() {
+                let mut state = match entries.contains(&address) {
+                    true => RESET,
+                    false => UNKNOWN,
+                };
+                for from in predecessors.get(&address).into_iter().flatten() {
+                    let previous = output(*from, input[from], &words);
+                    state.q &= previous.q;
+                    state.ir &= previous.ir;
+                }
+                if input[&address] != state {
+                    input.insert(address, state);
+                    changed = true;
+                }
+            }
+            if !changed {
+                break;
+            }
+        }
+
+        let mut warnings = vec![];
+        for (address, (instruction, access)) in words.iter() {
+            let mut warn = |span, kind| {
+                warnings.push(Warning {
+                    line: instruction.line,
+                    address: *address,
+                    span,
+                    kind,
+                })
+            };
+            let source = &instruction.source;
+            let previous = predecessors.get(address).into_iter().flatten();
+            let previous = previous.map(|from| &words[from]).collect::<Vec<_>>();
+            if access.mem && previous.iter().any(|(_, access)| !access.load_ar) {
+                warn(assignment_span(source), WarningKind::MemWithoutAr);
+            }
+            if access.load_ir
+                && previous
+                    .iter()
+                    .any(|(_, access)| access.ar_from_pc && !access.pc_step)
+            {
+                warn(assignment_span(source), WarningKind::PcNotIncremented);
+            }
+            if access.pc_step && !access.load_ar {
+                warn(
+                    keyword_span(source, "PC + 1"),
+                    WarningKind::PcStepWithoutFetch,
+                );
+            }
+            let state = input[address];
+            if access.read_q && !state.q {
+                warn(assignment_span(source), WarningKind::QBeforeWrite);
+            }
+            if access.ir108 && !state.ir {
+                warn(keyword_span(source, "CC#"), WarningKind::ConditionWithoutIr);
+            }
+        }
+        warnings
+    }
+}
@@ -0,0 +1,278 @@
+use crate::ast::token::Condition;
+use crate::grammar;
+use crate::parser::decoder::Decoder;
+use crate::parser::instrument::{Ci, DC1, DC2, Instrument, MEM, Mi20, Mi53, Mi86, SCi, SST};
+use crate::parser::parser::ExprParser;
+use crate::result::{Encoding, FieldState};
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// 由控存映像还原的一条微指令
+#[derive(Debug, Clone, Serialize)]
+pub struct DecompiledWord {
+    pub address: u16,
+    pub label: Option<String>,
+    pub expr: Option<String>, // 无法还原为表达式时为None
+    pub notes: Vec<String>,   // 表达式没有体现、但不为0的字段，如"MI53=2"
+    pub encoding: Encoding,
+}
+
+/// 整个控存映像的反汇编结果
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Decompiled {
+    words: Vec<DecompiledWord>,
+}
+
+impl Decompiled {
+    /// 逐个地址还原为表达式，NEXT指向的地址生成标号
+    pub fn new(image: &BTreeMap<u16, Vec<u8>>) -> Self {
+        let decoded = image
+            .iter()
+            .map(|(address, hex)| {
+                let decoder = Decoder::new(hex);
+                let result = decompile(&decoder);
+                (*address, decoder, result)
+            })
+            .collect::<Vec<_>>();
+        // 只给能还原的地址生成标号，否则重新编译时标号未定义
+        let labels = decoded
+            .iter()
+            .filter(|(_, decoder, _)| uses_next(decoder))
+            .map(|(_, decoder, _)| field(decoder, "NEXT"))
+            .filter(|target| {
+                decoded
+                    .iter()
+                    .any(|(address, _, result)| address == target && result.is_some())
+            })
+            .collect::<BTreeSet<_>>();
+        let target = |address: u16| match labels.contains(&address) {
+            true => label(address),
+            false => format!("0x{:03X}", address),
+        };
+        let words = decoded
+            .iter()
+            .map(|(address, decoder, result)| DecompiledWord {
+                address: *address,
+                label: labels.contains(address).then(|| label(*address)),
+                expr: result
+                    .as_ref()
+                    .map(|_| expr_text(decoder, &target).unwrap()),
+                notes: result
+                    .as_ref()
+                    .map_or_else(Vec::new, |(_, notes)| notes.clone()),
+                encoding: Encoding::from_decoder(decoder),
+            })
+            .collect();
+        Self { words }
+    }
+
+    pub fn words(&self) -> &[DecompiledWord] {
+        &self.words
+    }
+
+    /// 带注释的微程序，可以用build重新编译；无法还原的微指令只保留为注释
+    pub fn source(&self) -> String {
+        let mut output = String::new();
+        let mut next = None;
+        for word in self.words.iter() {
+            let hex = hex::encode_upper(&word.encoding.hex);
+            let Some(expr) = &word.expr else {
+                output.push_str(&format!(
+                    "; {:03X}: {} 无法还原为表达式\n",
+                    word.address, hex
+                ));
+                continue;
+            };
+            if next != Some(word.address) {
+                output.push_str(&format!("ORG {:03X}H\n", word.address));
+            }
+            next = Some(word.address + 1);
+            let label = word
+                .label
+                .as_ref()
+                .map_or(String::new(), |label| format!("{}:", label));
+            let mut comment = format!("{:03X}: {}", word.address, hex);
+            if !word.notes.is_empty() {
+                comment.push_str(&format!(" 未体现: {}", word.notes.join(", ")));
+            }
+            output.push_str(&format!("{:<7}{:<40} ; {}\n", label, expr, comment));
+        }
+        output
+    }
+}
+
+fn label(address: u16) -> String {
+    format!("L{:03X}", address)
+}
+
+fn field(decoder: &Decoder, key: &str) -> u16 {
+    decoder
+        .instrument(&Instrument::from_key(key).unwrap())
+        .value()
+}
+
+fn ci(decoder: &Decoder) -> Option<Ci> {
+    Ci::try_from(field(decoder, "CI") as u64).ok()
+}
+
+/// CI使用NEXT作为转移地址
+fn uses_next(decoder: &Decoder) -> bool {
+    matches!(
+        ci(decoder),
+        Some(Ci::CJS | Ci::IF | Ci::JSRP | Ci::CJV | Ci::JRP | Ci::RPCT | Ci::CJPP | Ci::TWB)
+    )
+}
+
+/// 把一条微指令还原为表达式，NEXT写为数字，重新编码后和原来不一致时返回None
+///
+/// 表达式没有涉及的字段按原来的值列出，这些字段重新编译后为0
+pub fn decompile(decoder: &Decoder) -> Option<(String, Vec<String>)> {
+    let text = expr_text(decoder, &|address| format!("0x{:03X}", address))?;
+    let expr = grammar::ExprParser::new().parse(&text).ok()?;
+    let mut parser = ExprParser::new(expr);
+    parser.parse().ok()?;
+    let encoded = Decoder::new(&parser.hex());
+    let mut notes = vec![];
+    for instrument in decoder.layout().instruments() {
+        let (expected, actual) = (
+            decoder.instrument(&instrument).value(),
+            encoded.instrument(&instrument).value(),
+        );
+        if expected != actual {
+            match parser.field_state(&instrument) {
+                FieldState::DontCare => notes.push(format!("{}={}", instrument.key(), expected)),
+                _ => return None,
+            }
+        }
+    }
+    Some((text, notes))
+}
+
+/// 按各个字段拼出表达式，不保证能编码回原来的微指令
+fn expr_text(decoder: &Decoder, target: &dyn Fn(u16) -> String) -> Option<String> {
+    let value = |key| field(decoder, key);
+    let mi20 = Mi20::try_from(value("MI20") as u64).ok()?;
+    let mi53 = Mi53::try_from(value("MI53") as u64).ok()?;
+    let mi86 = Mi86::try_from(value("MI86") as u64).ok()?;
+    let (sa, a, sb, b) = (value("SA"), value("A"), value("SB"), value("B"));
+    let mem = decoder.mem();
+    let mem_read = mem == MEM::MemRead;
+
+    // 运算器的两个输入
+    let a_name = match (sa, a) {
+        (1, _) => Some("SR".to_string()),
+        (0, 6) => Some("IP".to_string()),
+        _ => None,
+    };
+    let b_name = match (sb, b) {
+        (1, _) => Some("DR".to_string()),
+        (0, 6) => Some("IP".to_string()),
+        (0, n) if n <= 11 => Some(format!("R{}", n)),
+        _ => None,
+    };
+    let binary = |r: Option<String>, s: Option<String>| {
+        let (r, s) = (r?, s?);
+        match mi53 {
+            Mi53::RAddS => Some(format!("{} + {}", r, s)),
+            Mi53::RSubS => Some(format!("{} - {}", r, s)),
+            Mi53::SSubR => Some(format!("{} - {}", s, r)),
+            _ => None,
+        }
+    };
+    let term = match mi20 {
+        Mi20::D0 if mem_read => Some("MEM".to_string()),
+        Mi20::_0A if sa == 1 => Some("SR".to_string()),
+        Mi20::_0B if sb == 0 && b == 5 && sa == 0 && a == 5 && mi86 == Mi86::FBA => {
+            Some("PC".to_string())
+        }
+        Mi20::_0B => b_name.clone(),
+        Mi20::_0Q => Some("Q".to_string()),
+        Mi20::AQ => binary(a_name, Some("Q".to_string())),
+        Mi20::AB => binary(a_name, b_name),
+        Mi20::DA if mem_read && sa == 1 => binary(Some("MEM".to_string()), a_name),
+        Mi20::DA if mem_read && sb == 0 && b == 5 => binary(Some("MEM".to_string()), a_name),
+        Mi20::DQ if mem_read => binary(Some("MEM".to_string()), Some("Q".to_string())),
+        _ => None,
+    };
+
+    let pc_step = value("SCi") == SCi::PCStep as u16
+        && mi86 == Mi86::FBA
+        && sb == 0
+        && b == 5
+        && mi20 == Mi20::_0B;
+    let dc2 = DC2::try_from(value("DC2") as u64).ok();
+    let mem_write = mem == MEM::MemWrite && value("DC1") == DC1::FromALU as u16;
+    let destination = match mi86 {
+        Mi86::FQF => Some("Q".to_string()),
+        Mi86::FBF if sb == 1 => Some("DR".to_string()),
+        Mi86::FBF if b <= 11 && sa == 0 && a == b => Some(format!("R{}", b)),
+        Mi86::FBF if b == 5 => Some("PC".to_string()),
+        Mi86::FBF => return None,
+        Mi86::NONE | Mi86::FBA => match (&dc2, mem_write) {
+            (Some(DC2::AR), false) => Some("AR".to_string()),
+            (Some(DC2::IR), false) => Some("IR".to_string()),
+            (Some(DC2::None), true) => Some("MEM".to_string()),
+            (Some(DC2::None), false) => None,
+            _ => return None,
+        },
+        _ => return None,
+    };
+    // 写寄存器的同时不能再写AR、IR或主存
+    if matches!(mi86, Mi86::FQF | Mi86::FBF) && (dc2 != Some(DC2::None) || mem_write) {
+        return None;
+    }
+
+    let assignment = match (term, destination) {
+        (Some(term), Some(destination)) => Some(format!("{} -> {}", term, destination)),
+        (None, Some(_)) => return None,
+        _ => None,
+    };
+    let mut flags = vec![];
+    if pc_step {
+        flags.push("PC + 1 -> PC".to_string());
+    }
+    if value("SST") == SST::ALU as u16 {
+        flags.push("CarryFromALU".to_string());
+    }
+    let ci = ci(decoder)?;
+    let (scc, sc) = (value("SCC"), value("SC"));
+    let condition = decoder.condition().and_then(|condition| {
+        Some(match condition {
+            Condition::Zero => "0",
+            Condition::One => "1",
+            Condition::NotS => "/S",
+            Condition::NotV => "/V",
+            Condition::NotZ => "/Z",
+            Condition::NotC => "/C",
+            Condition::IR108 => "C",
+            _ => return None,
+        })
+    });
+    let with_condition = match ci {
+        Ci::SEQ => false,
+        Ci::IF => true,
+        _ => scc != 0 || sc != 0,
+    };
+    if with_condition {
+        flags.push(format!("CC#={}", condition?));
+    }
+    if !matches!(ci, Ci::SEQ | Ci::IF) {
+        flags.push(format!("CI={:?}", ci));
+    }
+    let next = value("NEXT");
+    if next != 0 || with_condition || uses_next(decoder) {
+        flags.push(format!("NEXT={}", target(next)));
+    }
+    // 全部为默认值时也要写出一个标志，否则是空行
+    if assignment.is_none() && flags.is_empty() {
+        flags.push("CI=SEQ".to_string());
+    }
+    Some(match assignment {
+        Some(assignment) => [assignment]
+            .into_iter()
+            .chain(flags)
+            .collect::<Vec<_>>()
+            .join(", "),
+        None => format!(", {}", flags.join(", ")),
+    })
+}
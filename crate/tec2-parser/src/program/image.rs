@@ -0,0 +1,271 @@
+use crate::parser::decoder::Decoder;
+use crate::parser::format::WordFormat;
+use crate::parser::layout::Layout;
+use crate::program::csv::from_csv;
+use crate::program::markdown::from_markdown;
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::{Display, Formatter};
+use std::sync::Arc;
+
+/// 控存映像文件的格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ImageFormat {
+    Binary,   // 每个地址按微指令的字节数依次排列，高字节在前
+    IntelHex, // 字节地址为微地址乘以微指令的字节数
+    Mif,      // Intel/Altera
+    Text,     // 每行一条十六进制微指令，可以带"地址:"前缀，也支持$readmemh的@地址
+    Csv,      // 每个字段一列，值为二进制
+    Markdown, // tec-2.py生成的字段表格
+}
+
+#[derive(Debug, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", content = "detail", rename_all = "snake_case")]
+pub enum ImageError {
+    InvalidLength(usize),                // 二进制文件的长度不是微指令字节数的整数倍
+    InvalidRecord(usize),                // 行号
+    InvalidChecksum(usize),              // 行号
+    InvalidWidth(u32),                   // .mif的WIDTH和微指令格式不一致
+    InvalidValue(usize, String),         // (行号, 值)
+    InvalidField(usize, String, String), // (行号, 字段, 值)，值不合法或超出字段的位数
+    AddressOutOfRange(u32),
+}
+
+impl ImageFormat {
+    /// 按内容判断：以':'开头为Intel HEX，含CONTENT BEGIN为.mif，扩展名为.bin时为二进制，.csv时为CSV，
+    /// .md或含有"|下地址|"时为Markdown表格，其余为文本
+    pub fn detect(extension: Option<&str>, data: &[u8]) -> Self {
+        if extension.is_some_and(|ext| ext.eq_ignore_ascii_case("bin")) {
+            return ImageFormat::Binary;
+        }
+        if extension.is_some_and(|ext| ext.eq_ignore_ascii_case("csv")) {
+            return ImageFormat::Csv;
+        }
+        if extension.is_some_and(|ext| ext.eq_ignore_ascii_case("md")) {
+            return ImageFormat::Markdown;
+        }
+        let Ok(text) = std::str::from_utf8(data) else {
+            return ImageFormat::Binary;
+        };
+        let upper = text.to_ascii_uppercase();
+        if text.trim_start().starts_with(':') {
+            ImageFormat::IntelHex
+        } else if upper.contains("CONTENT") && upper.contains("BEGIN") {
+            ImageFormat::Mif
+        } else if text.contains("|下地址|") {
+            ImageFormat::Markdown
+        } else {
+            ImageFormat::Text
+        }
+    }
+
+    /// 读取控存映像，返回每个地址的微指令
+    pub fn import(
+        &self,
+        data: &[u8],
+        layout: &Layout,
+    ) -> Result<BTreeMap<u16, Vec<u8>>, ImageError> {
+        let text = || String::from_utf8_lossy(data).into_owned();
+        match self {
+            ImageFormat::Binary if !data.len().is_multiple_of(layout.bytes()) => {
+                Err(ImageError::InvalidLength(data.len()))
+            }
+            ImageFormat::Binary => from_bytes(&data.iter().copied().enumerate().collect(), layout),
+            ImageFormat::IntelHex => from_bytes(&intel_hex_bytes(&text())?, layout),
+            ImageFormat::Mif => from_mif(&text(), layout),
+            ImageFormat::Text => from_text(&text(), layout),
+            ImageFormat::Csv => from_csv(&text(), layout),
+            ImageFormat::Markdown => from_markdown(&text(), layout),
+        }
+    }
+}
+
+fn check_address(address: u32, layout: &Layout) -> Result<u16, ImageError> {
+    match address <= layout.max_address() as u32 {
+        true => Ok(address as u16),
+        false => Err(ImageError::AddressOutOfRange(address)),
+    }
+}
+
+/// 按字节地址把连续的字节组合为微指令
+fn from_bytes(
+    bytes: &BTreeMap<usize, u8>,
+    layout: &Layout,
+) -> Result<BTreeMap<u16, Vec<u8>>, ImageError> {
+    let size = layout.bytes();
+    let mut image = BTreeMap::new();
+    for address in bytes.keys().map(|x| x / size).collect::<BTreeSet<_>>() {
+        let hex = (0..size)
+            .map(|i| bytes.get(&(address * size + i)).copied().unwrap_or(0))
+            .collect();
+        image.insert(check_address(address as u32, layout)?, hex);
+    }
+    Ok(image)
+}
+
+/// 解析Intel HEX，返回字节地址到字节的映射
+pub fn intel_hex_bytes(text: &str) -> Result<BTreeMap<usize, u8>, ImageError> {
+    let mut bytes = BTreeMap::new();
+    let mut base = 0usize;
+    for (index, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        let record = line
+            .strip_prefix(':')
+            .and_then(|x| hex::decode(x).ok())
+            .filter(|x| x.len() >= 5 && x.len() == x[0] as usize + 5)
+            .ok_or(ImageError::InvalidRecord(index + 1))?;
+        if record.iter().fold(0u8, |sum, x| sum.wrapping_add(*x)) != 0 {
+            return Err(ImageError::InvalidChecksum(index + 1));
+        }
+        let address = u16::from_be_bytes([record[1], record[2]]) as usize;
+        let data = &record[4..record.len() - 1];
+        match record[3] {
+            0 => {
+                for (i, byte) in data.iter().enumerate() {
+                    bytes.insert(base + address + i, *byte);
+                }
+            }
+            1 => break,
+            2 if data.len() == 2 => base = (u16::from_be_bytes([data[0], data[1]]) as usize) << 4,
+            4 if data.len() == 2 => base = (u16::from_be_bytes([data[0], data[1]]) as usize) << 16,
+            3 | 5 => {}
+            _ => return Err(ImageError::InvalidRecord(index + 1)),
+        }
+    }
+    Ok(bytes)
+}
+
+fn to_hex(value: u64, layout: &Layout) -> Vec<u8> {
+    value.to_be_bytes()[8 - layout.bytes()..].to_vec()
+}
+
+fn from_mif(text: &str, layout: &Layout) -> Result<BTreeMap<u16, Vec<u8>>, ImageError> {
+    let mut image = BTreeMap::new();
+    let mut address_radix = 16;
+    let mut data_radix = 16;
+    let mut content = false;
+    let radix = |value: &str| match value.trim().to_ascii_uppercase().as_str() {
+        "BIN" => 2,
+        "OCT" => 8,
+        "DEC" | "UNS" => 10,
+        _ => 16,
+    };
+    for (index, line) in text.lines().enumerate() {
+        let line = line.split("--").next().unwrap_or("").trim();
+        let error = |value: &str| ImageError::InvalidValue(index + 1, value.to_string());
+        let upper = line.to_ascii_uppercase();
+        if upper.starts_with("CONTENT") {
+            content = true;
+            continue;
+        }
+        if !content {
+            let Some((key, value)) = line.trim_end_matches(';').split_once('=') else {
+                continue;
+            };
+            match key.trim().to_ascii_uppercase().as_str() {
+                "WIDTH" => {
+                    let width = value.trim().parse().map_err(|_| error(value))?;
+                    if width != layout.width as u32 {
+                        return Err(ImageError::InvalidWidth(width));
+                    }
+                }
+                "ADDRESS_RADIX" => address_radix = radix(value),
+                "DATA_RADIX" => data_radix = radix(value),
+                _ => {}
+            }
+            continue;
+        }
+        if upper.starts_with("END") {
+            break;
+        }
+        let Some((address, values)) = line.trim_end_matches(';').split_once(':') else {
+            continue;
+        };
+        let address = address.trim();
+        let (start, end) = match address.strip_prefix('[').and_then(|x| x.strip_suffix(']')) {
+            Some(range) => range.split_once("..").ok_or_else(|| error(address))?,
+            None => (address, address),
+        };
+        let parse_address =
+            |x: &str| u32::from_str_radix(x.trim(), address_radix).map_err(|_| error(x));
+        let (start, end) = (parse_address(start)?, parse_address(end)?);
+        let values = values
+            .split_whitespace()
+            .map(|x| {
+                u64::from_str_radix(x, data_radix)
+                    .ok()
+                    .filter(|value| value.checked_shr(layout.width as u32).unwrap_or(0) == 0)
+                    .ok_or_else(|| error(x))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        if values.is_empty() {
+            return Err(error(line));
+        }
+        // 范围内按顺序重复给出的值
+        for (i, address) in (start..=end.max(start + values.len() as u32 - 1)).enumerate() {
+            let hex = to_hex(values[i % values.len()], layout);
+            image.insert(check_address(address, layout)?, hex);
+        }
+    }
+    Ok(image)
+}
+
+fn from_text(text: &str, layout: &Layout) -> Result<BTreeMap<u16, Vec<u8>>, ImageError> {
+    let shared = Arc::new(layout.clone());
+    let mut image = BTreeMap::new();
+    let mut address = 0u32;
+    for (index, line) in text.lines().enumerate() {
+        let line = line.split([';', '#']).next().unwrap_or("");
+        let line = line.split("//").next().unwrap_or("").trim();
+        let error = |value: &str| ImageError::InvalidValue(index + 1, value.to_string());
+        if line.is_empty() {
+            continue;
+        }
+        if let Some(start) = line.strip_prefix('@') {
+            address = u32::from_str_radix(start.trim(), 16).map_err(|_| error(start))?;
+            continue;
+        }
+        let value = match line.split_once(':') {
+            Some((start, value)) => {
+                address = u32::from_str_radix(start.trim(), 16).map_err(|_| error(start))?;
+                value
+            }
+            None => line,
+        };
+        let decoder = Decoder::from_words(value, &WordFormat::default(), shared.clone())
+            .map_err(|_| error(value.trim()))?;
+        image.insert(check_address(address, layout)?, decoder.hex());
+        address += 1;
+    }
+    Ok(image)
+}
+
+impl Display for ImageError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ImageError::InvalidLength(length) => {
+                write!(f, "文件长度{}不是微指令字节数的整数倍", length)
+            }
+            ImageError::InvalidRecord(line) => write!(f, "第{}行: 不合法的Intel HEX记录", line),
+            ImageError::InvalidChecksum(line) => write!(f, "第{}行: 校验和错误", line),
+            ImageError::InvalidWidth(width) => {
+                write!(f, "映像的位数{}和微指令格式不一致", width)
+            }
+            ImageError::InvalidValue(line, value) => write!(f, "第{}行: 不合法的值{}", line, value),
+            ImageError::InvalidField(line, field, value) => {
+                write!(
+                    f,
+                    "第{}行: 字段{}的值{}不合法或超出位数",
+                    line, field, value
+                )
+            }
+            ImageError::AddressOutOfRange(address) => {
+                write!(f, "地址超出范围: {:X}H", address)
+            }
+        }
+    }
+}
@@ -0,0 +1,122 @@
+use crate::program::{MicroInstruction, Program};
+use crate::result::{Encoding, FieldState};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap};
+
+/// 列表文件的格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ListingFormat {
+    #[default]
+    Text, // 每条微指令一行，下一行为字段注释
+    Markdown, // 表格，用于实验报告
+}
+
+impl Program {
+    /// 按源代码顺序列出地址、编码、按字段分组的二进制、源代码和字段说明
+    pub fn listing(&self, format: ListingFormat) -> String {
+        let mut labels: HashMap<u16, Vec<&str>> = HashMap::new();
+        for instruction in self.instructions() {
+            for label in instruction.labels.iter() {
+                labels.entry(instruction.address).or_default().push(label);
+            }
+        }
+        let rows = self
+            .instructions()
+            .iter()
+            .map(|instruction| Row::new(instruction, &labels))
+            .collect::<Vec<_>>();
+        match format {
+            ListingFormat::Text => rows.iter().map(Row::text).collect(),
+            ListingFormat::Markdown => {
+                let mut output = "|地址|微码|二进制|标号|源代码|字段|\n".to_string();
+                output.push_str("|:-:|:-:|:-:|:-:|:--|:--|\n");
+                for row in rows.iter() {
+                    output.push_str(&row.markdown());
+                }
+                output
+            }
+        }
+    }
+}
+
+struct Row {
+    address: u16,
+    hex: String,
+    bits: String,
+    labels: String,
+    source: String,
+    comments: Vec<String>,
+}
+
+impl Row {
+    fn new(instruction: &MicroInstruction, labels: &HashMap<u16, Vec<&str>>) -> Self {
+        let encoding = &instruction.encoding;
+        // 只列出表达式指定的字段，下地址换成对应的标号
+        let comments = encoding
+            .fields
+            .iter()
+            .filter(|field| field.state == FieldState::Set)
+            .map(|field| match field.name {
+                "下地址" => match labels.get(&field.value) {
+                    Some(names) => format!("下地址={}({:03X}H)", names.join("/"), field.value),
+                    None => format!("下地址={:03X}H", field.value),
+                },
+                name => format!("{}={}({})", name, field.bits, field.meaning),
+            })
+            .collect();
+        Self {
+            address: instruction.address,
+            hex: hex::encode_upper(&encoding.hex),
+            bits: grouped_bits(encoding),
+            labels: instruction.labels.join(", "),
+            source: instruction.source.clone(),
+            comments,
+        }
+    }
+
+    fn text(&self) -> String {
+        let label = match self.labels.is_empty() {
+            true => String::new(),
+            false => format!("{}:", self.labels),
+        };
+        let mut output = format!(
+            "{:03X}  {}  {}  {:<7}{}\n",
+            self.address, self.hex, self.bits, label, self.source
+        );
+        if !self.comments.is_empty() {
+            output.push_str(&format!("     ; {}\n", self.comments.join(", ")));
+        }
+        output
+    }
+
+    fn markdown(&self) -> String {
+        let escape = |text: &str| text.replace('|', "\\|");
+        format!(
+            "|{:03X}H|`{}`|`{}`|{}|`{}`|{}|\n",
+            self.address,
+            self.hex,
+            self.bits,
+            escape(&self.labels),
+            escape(&self.source),
+            escape(&self.comments.join("<br>"))
+        )
+    }
+}
+
+/// 在字段的边界处用空格分隔二进制
+fn grouped_bits(encoding: &Encoding) -> String {
+    let boundaries = encoding
+        .fields
+        .iter()
+        .flat_map(|field| [field.begin as usize, field.begin as usize + field.width])
+        .collect::<BTreeSet<_>>();
+    let mut output = String::new();
+    for (i, bit) in encoding.bin.chars().enumerate() {
+        if i > 0 && boundaries.contains(&i) {
+            output.push(' ');
+        }
+        output.push(bit);
+    }
+    output
+}
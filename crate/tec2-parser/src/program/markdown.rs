@@ -0,0 +1,100 @@
+use crate::parser::instrument::Instrument;
+use crate::parser::layout::Layout;
+use crate::program::ImageError;
+use crate::program::csv::Columns;
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+/// 表格中的一行微指令
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TableRow {
+    pub line: usize, // 从1开始的行号
+    pub address: Option<u16>,
+    #[serde(serialize_with = "crate::result::serialize_hex")]
+    pub hex: Vec<u8>,
+}
+
+/// 读取tec-2.py的Instruct.table生成的Markdown表格，每行一条微指令
+///
+/// ```text
+/// |下地址|备用|CI|SCC|SC|备用|SST|MIO|MI8-6|REQ|MI5-3|WE|MI2-0|A口|B口|SCi|SSH|SA|DC1|SB|DC2|
+/// |-|-|-|-|-|-|-|-|-|-|-|-|-|-|-|-|-|-|-|-|-|
+/// |0000000000|00|1110|000|0|0|000|1|001|0|000|0|011|0000|0000|00|00|1|000|1|010|
+/// ```
+///
+/// 文件中可以有多个表格，表头中没有下地址的表格和表格之外的内容被忽略，
+/// 表头和CSV一样按列名对应字段，备用列被忽略
+pub fn markdown_tables(text: &str, layout: &Layout) -> Result<Vec<TableRow>, ImageError> {
+    let mut rows = vec![];
+    let mut lines = text.lines().enumerate().peekable();
+    while let Some((index, line)) = lines.next() {
+        let Some(header) = cells(line) else {
+            continue;
+        };
+        // 表头的下一行必须是分隔行
+        let separator = lines.peek().and_then(|(_, line)| cells(line));
+        if !separator.is_some_and(|cells| cells.iter().all(|cell| is_separator(cell))) {
+            continue;
+        }
+        lines.next();
+        // 其他表格的列名可能恰好是A、B之类的字段名，所以要求有下地址列
+        let next = header
+            .iter()
+            .any(|cell| matches!(Instrument::from_key(cell), Some(Instrument::NEXT(_))));
+        let Some(columns) = next
+            .then(|| Columns::new(&header, layout, index + 1).ok())
+            .flatten()
+        else {
+            continue;
+        };
+        while let Some(cells) = lines.peek().and_then(|(_, line)| cells(line)) {
+            let (index, _) = lines.next().unwrap();
+            let (address, hex) = columns.encode(&cells, layout, index + 1)?;
+            let address = address
+                .map(|address| match address <= layout.max_address() as u32 {
+                    true => Ok(address as u16),
+                    false => Err(ImageError::AddressOutOfRange(address)),
+                })
+                .transpose()?;
+            rows.push(TableRow {
+                line: index + 1,
+                address,
+                hex,
+            });
+        }
+    }
+    Ok(rows)
+}
+
+/// 按表格中的地址列排列，没有地址列时从0开始依次排列
+pub(crate) fn from_markdown(
+    text: &str,
+    layout: &Layout,
+) -> Result<BTreeMap<u16, Vec<u8>>, ImageError> {
+    let mut image = BTreeMap::new();
+    let mut address = 0u32;
+    for row in markdown_tables(text, layout)? {
+        address = row.address.map_or(address, u32::from);
+        if address > layout.max_address() as u32 {
+            return Err(ImageError::AddressOutOfRange(address));
+        }
+        image.insert(address as u16, row.hex);
+        address += 1;
+    }
+    Ok(image)
+}
+
+/// 表格的一行，去掉首尾的'|'和单元格中的'`'，不是表格时为None
+fn cells(line: &str) -> Option<Vec<String>> {
+    let line = line.trim().strip_prefix('|')?;
+    let line = line.strip_suffix('|').unwrap_or(line);
+    Some(
+        line.split('|')
+            .map(|cell| cell.trim().trim_matches('`').trim().to_string())
+            .collect(),
+    )
+}
+
+fn is_separator(cell: &str) -> bool {
+    cell.contains('-') && cell.chars().all(|c| matches!(c, '-' | ':' | ' '))
+}
@@ -0,0 +1,19 @@
+mod analysis;
+mod cfg;
+mod csv;
+mod dataflow;
+mod decompile;
+mod image;
+mod listing;
+mod markdown;
+mod rom;
+mod source;
+
+pub use analysis::*;
+pub use cfg::*;
+pub use decompile::*;
+pub use image::*;
+pub use listing::*;
+pub use markdown::*;
+pub use rom::*;
+pub use source::*;
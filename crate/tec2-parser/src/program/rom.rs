@@ -0,0 +1,261 @@
+use crate::parser::format::WordOrder;
+use crate::parser::layout::Layout;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt::{Display, Formatter};
+use std::ops::RangeInclusive;
+
+/// 按芯片拆分控存的方式
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChipSlicing {
+    pub width: u8,                  // 每片的数据位数，1到16
+    pub order: WordOrder,           // HighFirst时第0片为微指令的最高位
+    pub reverse: bool,              // 片内数据位反序，D0接微指令的高位
+    pub range: RangeInclusive<u16>, // 导出的地址范围
+    pub fill: u64,                  // 没有微指令的地址填充的值
+}
+
+/// 一片芯片的内容，data按地址排列，从range的起始地址开始
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Chip {
+    pub index: usize,
+    pub begin: u8, // 对应微指令的位置，从最高位开始数
+    pub width: u8,
+    pub start: u16,
+    pub data: Vec<u16>,
+}
+
+/// 硬件描述语言和仿真软件使用的存储器初始化文件
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MemoryFormat {
+    ReadMemH, // Verilog的$readmemh
+    ReadMemB, // Verilog的$readmemb
+    Coe,      // Xilinx
+    Mif,      // Intel/Altera
+    Logisim,  // Logisim的v2.0 raw
+}
+
+#[derive(Debug, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", content = "detail", rename_all = "snake_case")]
+pub enum RomError {
+    InvalidChipWidth(u8),
+    InvalidRange(u16, u16),
+    InvalidFill(u64),
+}
+
+impl ChipSlicing {
+    /// 8位宽的芯片，高位在第0片，范围为layout的全部地址
+    pub fn new(layout: &Layout) -> Self {
+        Self {
+            width: 8,
+            order: WordOrder::HighFirst,
+            reverse: false,
+            range: 0..=layout.max_address(),
+            fill: 0,
+        }
+    }
+
+    /// 把控存拆分为多片，最后一片不满时高位补0
+    pub fn slice(
+        &self,
+        image: &BTreeMap<u16, Vec<u8>>,
+        layout: &Layout,
+    ) -> Result<Vec<Chip>, RomError> {
+        if self.width == 0 || self.width > 16 {
+            return Err(RomError::InvalidChipWidth(self.width));
+        }
+        let words = words(image, layout, self.range.clone(), self.fill)?;
+        let count = (layout.width as usize).div_ceil(self.width as usize);
+        let mut chips = (0..count)
+            .map(|index| {
+                let begin = (index * self.width as usize) as u8;
+                let width = self.width.min(layout.width - begin);
+                let shift = layout.width - begin - width;
+                let data = words
+                    .iter()
+                    .map(|word| {
+                        let value = ((word >> shift) & ((1 << width) - 1)) as u16;
+                        match self.reverse {
+                            true => value.reverse_bits() >> (16 - width),
+                            false => value,
+                        }
+                    })
+                    .collect();
+                Chip {
+                    index,
+                    begin,
+                    width,
+                    start: *self.range.start(),
+                    data,
+                }
+            })
+            .collect::<Vec<_>>();
+        if self.order == WordOrder::LowFirst {
+            chips.reverse();
+            for (index, chip) in chips.iter_mut().enumerate() {
+                chip.index = index;
+            }
+        }
+        Ok(chips)
+    }
+}
+
+impl Chip {
+    /// 每个地址的字节数，8位以下为1
+    pub fn bytes(&self) -> usize {
+        (self.width as usize).div_ceil(8)
+    }
+
+    /// 二进制文件，16位宽时高字节在前
+    pub fn to_bin(&self) -> Vec<u8> {
+        self.data
+            .iter()
+            .flat_map(|value| match self.bytes() {
+                1 => vec![*value as u8],
+                _ => value.to_be_bytes().to_vec(),
+            })
+            .collect()
+    }
+
+    /// Intel HEX文件，记录的地址为芯片中的字节地址
+    pub fn to_intel_hex(&self) -> String {
+        intel_hex(self.start as u32 * self.bytes() as u32, &self.to_bin())
+    }
+}
+
+impl MemoryFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            MemoryFormat::ReadMemH => "memh",
+            MemoryFormat::ReadMemB => "memb",
+            MemoryFormat::Coe => "coe",
+            MemoryFormat::Mif => "mif",
+            MemoryFormat::Logisim => "txt",
+        }
+    }
+
+    /// 导出控存的全部地址，没有微指令的地址为fill
+    pub fn export(
+        &self,
+        image: &BTreeMap<u16, Vec<u8>>,
+        layout: &Layout,
+        fill: u64,
+    ) -> Result<String, RomError> {
+        let words = words(image, layout, 0..=layout.max_address(), fill)?;
+        let width = layout.width as usize;
+        let digits = width.div_ceil(4);
+        let hex = |word: &u64| format!("{:0digits$X}", word);
+        let output = match self {
+            MemoryFormat::ReadMemH => words.iter().map(|word| hex(word) + "\n").collect(),
+            MemoryFormat::ReadMemB => words
+                .iter()
+                .map(|word| format!("{:0width$b}\n", word))
+                .collect(),
+            MemoryFormat::Coe => format!(
+                "memory_initialization_radix=16;\nmemory_initialization_vector=\n{};\n",
+                words.iter().map(hex).collect::<Vec<_>>().join(",\n")
+            ),
+            MemoryFormat::Mif => {
+                let address_digits = (words.len() as u32 - 1).max(1).ilog(16) as usize + 1;
+                let content = words
+                    .iter()
+                    .enumerate()
+                    .map(|(address, word)| {
+                        format!("\t{:0address_digits$X} : {};\n", address, hex(word))
+                    })
+                    .collect::<String>();
+                format!(
+                    "WIDTH={};\nDEPTH={};\n\nADDRESS_RADIX=HEX;\nDATA_RADIX=HEX;\n\nCONTENT BEGIN\n{}END;\n",
+                    width,
+                    words.len(),
+                    content
+                )
+            }
+            MemoryFormat::Logisim => {
+                // 连续相同的值写为"个数*值"
+                let mut runs: Vec<(usize, u64)> = vec![];
+                for word in words.iter() {
+                    match runs.last_mut() {
+                        Some((count, last)) if last == word => *count += 1,
+                        _ => runs.push((1, *word)),
+                    }
+                }
+                let runs = runs
+                    .iter()
+                    .map(|(count, word)| match count {
+                        1 => format!("{:x}", word),
+                        _ => format!("{}*{:x}", count, word),
+                    })
+                    .collect::<Vec<_>>();
+                format!("v2.0 raw\n{}\n", runs.join(" "))
+            }
+        };
+        Ok(output)
+    }
+}
+
+/// 把控存转换为按地址排列的整数，没有微指令的地址为fill
+pub fn words(
+    image: &BTreeMap<u16, Vec<u8>>,
+    layout: &Layout,
+    range: RangeInclusive<u16>,
+    fill: u64,
+) -> Result<Vec<u64>, RomError> {
+    if range.is_empty() || *range.end() > layout.max_address() {
+        return Err(RomError::InvalidRange(*range.start(), *range.end()));
+    }
+    if layout.width < 64 && fill >> layout.width != 0 {
+        return Err(RomError::InvalidFill(fill));
+    }
+    Ok(range
+        .map(|address| {
+            image.get(&address).map_or(fill, |hex| {
+                hex.iter().fold(0u64, |word, byte| word << 8 | *byte as u64)
+            })
+        })
+        .collect())
+}
+
+/// 每条数据记录16字节，超过64K时使用扩展线性地址记录
+pub fn intel_hex(address: u32, data: &[u8]) -> String {
+    let mut output = String::new();
+    let mut upper = 0;
+    for (i, chunk) in data.chunks(16).enumerate() {
+        let address = address + i as u32 * 16;
+        if address >> 16 != upper {
+            upper = address >> 16;
+            output.push_str(&intel_hex_record(0, 4, &(upper as u16).to_be_bytes()));
+        }
+        output.push_str(&intel_hex_record(address as u16, 0, chunk));
+    }
+    output.push_str(&intel_hex_record(0, 1, &[]));
+    output
+}
+
+fn intel_hex_record(address: u16, kind: u8, data: &[u8]) -> String {
+    let mut bytes = vec![data.len() as u8];
+    bytes.extend(address.to_be_bytes());
+    bytes.push(kind);
+    bytes.extend(data);
+    let checksum = bytes
+        .iter()
+        .fold(0u8, |sum, byte| sum.wrapping_add(*byte))
+        .wrapping_neg();
+    bytes.push(checksum);
+    format!(":{}\n", hex::encode_upper(bytes))
+}
+
+impl Display for RomError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RomError::InvalidChipWidth(width) => {
+                write!(f, "芯片的位数必须在1到16之间: {}", width)
+            }
+            RomError::InvalidRange(start, end) => {
+                write!(f, "不合法的地址范围: {:03X}H-{:03X}H", start, end)
+            }
+            RomError::InvalidFill(fill) => write!(f, "填充值超出了微指令的位数: {:X}H", fill),
+        }
+    }
+}
@@ -0,0 +1,278 @@
+use crate::Error;
+use crate::ast::token::{Identifier, Target};
+use crate::compile::split;
+use crate::grammar;
+use crate::parser::layout::Layout;
+use crate::result::{Encoding, ParseResult};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::{Display, Formatter};
+
+/// 微程序中的一条微指令
+#[derive(Debug, Clone, Serialize)]
+pub struct MicroInstruction {
+    pub address: u16,
+    pub labels: Vec<String>,
+    pub line: usize, // 从1开始的行号
+    pub source: String,
+    pub encoding: Encoding,
+}
+
+/// 由多行表达式组成的微程序
+///
+/// ```text
+/// ; 注释
+/// ORG 0A0H
+/// FETCH: PC -> AR, PC + 1 -> PC
+///        MEM -> DR, NEXT=FETCH, CC#=0
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Program {
+    instructions: Vec<MicroInstruction>,
+    labels: HashMap<String, u16>, // 包括文件末尾没有微指令的标号
+}
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "kind", content = "detail", rename_all = "snake_case")]
+pub enum ProgramErrorKind {
+    Syntax(String),
+    Expr(Error),
+    InvalidLabel(String),
+    ReservedLabel(String), // 和寄存器名、关键字相同，NEXT=中无法使用
+    DuplicateLabel(String),
+    InvalidNumber(String),
+    AddressOutOfRange(u32),
+    AddressOverlap(u16),
+}
+
+#[derive(Debug, Serialize)]
+pub struct ProgramError {
+    pub line: usize,
+    pub kind: ProgramErrorKind,
+}
+
+struct Line {
+    line: usize,
+    address: u16,
+    labels: Vec<String>,
+    steps: Vec<String>,
+}
+
+impl Program {
+    pub fn parse(input: &str) -> Result<Self, ProgramError> {
+        Self::compile(input, &[])
+    }
+
+    /// 一个周期完成不了的表达式按compile拆成多条微指令，依次占用后面的地址，标号指向第一条，scratch为空时用Q暂存
+    pub fn compile(input: &str, scratch: &[Identifier]) -> Result<Self, ProgramError> {
+        // 拆分只和字段是否冲突有关，标号的地址先用0代替，拆出的各条微指令再按实际地址编码
+        let (_, labels) = Self::scan(input, |source| Ok(vec![source.to_string()]))?;
+        let labels = labels.into_keys().map(|label| (label, 0)).collect();
+        let (lines, labels) = Self::scan(input, |source| {
+            let steps = split(source, scratch, &labels).map_err(error_kind)?;
+            Ok(steps.into_iter().map(|step| step.source).collect())
+        })?;
+        let mut instructions = Vec::with_capacity(lines.len());
+        for line in lines {
+            for (i, source) in line.steps.into_iter().enumerate() {
+                let encoding = match ParseResult::parse_with_labels(&source, &labels) {
+                    ParseResult::Result(encoding) => encoding,
+                    result => {
+                        return Err(ProgramError {
+                            line: line.line,
+                            kind: error_kind(result),
+                        });
+                    }
+                };
+                instructions.push(MicroInstruction {
+                    address: line.address + i as u16,
+                    labels: match i {
+                        0 => line.labels.clone(),
+                        _ => vec![],
+                    },
+                    line: line.line,
+                    source,
+                    encoding,
+                });
+            }
+        }
+        Ok(Self {
+            instructions,
+            labels,
+        })
+    }
+
+    /// 第一遍扫描，确定每条微指令的地址和标号
+    ///
+    /// steps为一行表达式拆成的各条微指令
+    fn scan(
+        input: &str,
+        steps: impl Fn(&str) -> Result<Vec<String>, ProgramErrorKind>,
+    ) -> Result<(Vec<Line>, HashMap<String, u16>), ProgramError> {
+        let mut lines = vec![];
+        let mut labels = HashMap::new();
+        let mut pending_labels = vec![];
+        let mut label_line = 0;
+        let mut address = 0u32;
+        let max_address = Layout::active().max_address() as u32;
+        for (index, text) in input.lines().enumerate() {
+            let line = index + 1;
+            let error = |kind| ProgramError { line, kind };
+            let mut text = strip_comment(text).trim();
+            if let Some(operand) = org_operand(text) {
+                address = parse_number(operand)
+                    .ok_or_else(|| error(ProgramErrorKind::InvalidNumber(operand.to_string())))?;
+                if address > max_address {
+                    return Err(error(ProgramErrorKind::AddressOutOfRange(address)));
+                }
+                continue;
+            }
+            while let Some((label, rest)) = text.split_once(':') {
+                let label = label.trim();
+                if !is_label(label) {
+                    return Err(error(ProgramErrorKind::InvalidLabel(label.to_string())));
+                }
+                if !matches!(
+                    grammar::TargetParser::new().parse(label),
+                    Ok(Target::Label(_))
+                ) {
+                    return Err(error(ProgramErrorKind::ReservedLabel(label.to_string())));
+                }
+                if labels.contains_key(label) || pending_labels.iter().any(|x| x == label) {
+                    return Err(error(ProgramErrorKind::DuplicateLabel(label.to_string())));
+                }
+                pending_labels.push(label.to_string());
+                label_line = line;
+                text = rest.trim();
+            }
+            if text.is_empty() {
+                continue;
+            }
+            let steps = steps(text).map_err(error)?;
+            let count = steps.len() as u32;
+            if address + count - 1 > max_address {
+                return Err(error(ProgramErrorKind::AddressOutOfRange(
+                    address + count - 1,
+                )));
+            }
+            for label in pending_labels.iter() {
+                labels.insert(label.clone(), address as u16);
+            }
+            lines.push(Line {
+                line,
+                address: address as u16,
+                labels: std::mem::take(&mut pending_labels),
+                steps,
+            });
+            address += count;
+        }
+        // 文件末尾的标号指向下一个地址，必须在控存范围内
+        if !pending_labels.is_empty() && address > max_address {
+            return Err(ProgramError {
+                line: label_line,
+                kind: ProgramErrorKind::AddressOutOfRange(address),
+            });
+        }
+        for label in pending_labels {
+            labels.insert(label, address as u16);
+        }
+        Ok((lines, labels))
+    }
+
+    /// 标号对应的地址
+    pub fn label(&self, name: &str) -> Option<u16> {
+        self.labels.get(name).copied()
+    }
+
+    /// 按源代码顺序排列的微指令
+    pub fn instructions(&self) -> &[MicroInstruction] {
+        &self.instructions
+    }
+
+    /// 按地址排列的控存内容，地址重叠时报错
+    pub fn image(&self) -> Result<BTreeMap<u16, Vec<u8>>, ProgramError> {
+        let mut image = BTreeMap::new();
+        for instruction in self.instructions.iter() {
+            if image
+                .insert(instruction.address, instruction.encoding.hex.clone())
+                .is_some()
+            {
+                return Err(ProgramError {
+                    line: instruction.line,
+                    kind: ProgramErrorKind::AddressOverlap(instruction.address),
+                });
+            }
+        }
+        Ok(image)
+    }
+}
+
+fn error_kind(result: ParseResult) -> ProgramErrorKind {
+    match result {
+        ParseResult::ExprParseError { error } => ProgramErrorKind::Expr(error),
+        result => ProgramErrorKind::Syntax(result.to_string()),
+    }
+}
+
+pub(crate) fn strip_comment(text: &str) -> &str {
+    let end = [text.find(';'), text.find("//")]
+        .into_iter()
+        .flatten()
+        .min()
+        .unwrap_or(text.len());
+    &text[..end]
+}
+
+pub(crate) fn org_operand(text: &str) -> Option<&str> {
+    let (directive, operand) = text.split_once(char::is_whitespace)?;
+    directive
+        .eq_ignore_ascii_case("ORG")
+        .then_some(operand.trim())
+}
+
+pub(crate) fn is_label(label: &str) -> bool {
+    let mut chars = label.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// 支持十进制、0x前缀和H后缀的十六进制
+pub fn parse_number(text: &str) -> Option<u32> {
+    let text = text.trim();
+    if let Some(hex) = text.strip_prefix("0x").or(text.strip_prefix("0X")) {
+        u32::from_str_radix(hex, 16).ok()
+    } else if let Some(hex) = text.strip_suffix('H').or(text.strip_suffix('h')) {
+        u32::from_str_radix(hex, 16).ok()
+    } else {
+        text.parse().ok()
+    }
+}
+
+impl Display for ProgramErrorKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ProgramErrorKind::Syntax(message) => write!(f, "语法错误: {}", message),
+            ProgramErrorKind::Expr(error) => write!(f, "表达式解析错误: {}", error),
+            ProgramErrorKind::InvalidLabel(label) => write!(f, "不合法的标号: {}", label),
+            ProgramErrorKind::ReservedLabel(label) => {
+                write!(f, "标号不能和寄存器名或关键字相同: {}", label)
+            }
+            ProgramErrorKind::DuplicateLabel(label) => write!(f, "标号重复定义: {}", label),
+            ProgramErrorKind::InvalidNumber(number) => write!(f, "不合法的数字: {}", number),
+            ProgramErrorKind::AddressOutOfRange(address) => {
+                write!(f, "地址超出范围: {:X}H", address)
+            }
+            ProgramErrorKind::AddressOverlap(address) => {
+                write!(f, "地址 {:03X}H 被重复使用", address)
+            }
+        }
+    }
+}
+
+impl Display for ProgramError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "第{}行: {}", self.line, self.kind)
+    }
+}
@@ -0,0 +1,272 @@
+use crate::grammar;
+use crate::parser::decoder::Decoder;
+use crate::parser::format::OutputMode;
+use crate::parser::instrument::Instrument;
+use crate::parser::layout::Layout;
+use crate::parser::parser::ExprParser;
+use crate::{Diagnostic, Error, Lint, Severity};
+use lalrpop_util::ParseError;
+use serde::{Serialize, Serializer};
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use std::sync::Arc;
+
+/// 字段的值从哪里来
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FieldState {
+    Set,      // 表达式指定
+    Default,  // 表达式未指定，使用默认值
+    DontCare, // 表达式未使用，可以任意取值
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Field {
+    pub name: &'static str,
+    pub begin: u8,
+    pub width: usize,
+    pub value: u16,
+    pub bits: String,
+    pub meaning: String,
+    pub state: FieldState,
+    pub instrument: Instrument,
+}
+
+/// 一条微指令的编码结果
+#[derive(Debug, Clone, Serialize)]
+pub struct Encoding {
+    #[serde(serialize_with = "serialize_hex")]
+    pub hex: Vec<u8>,
+    pub bin: String,
+    pub fields: Vec<Field>,
+    #[serde(skip)]
+    pub lints: Vec<Lint>, // 解析表达式时发现的可疑之处
+}
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ParseResult {
+    InvalidToken {
+        location: usize,
+    },
+    UnrecognizedEof {
+        location: usize,
+        expected: Vec<String>,
+    },
+    UnrecognizedToken {
+        token: (usize, String, usize),
+        expected: Vec<String>,
+    },
+    ExprParseError {
+        error: Error,
+    },
+    Result(Encoding),
+}
+
+/// 带上输入和错误信息的解析结果，用于输出JSON
+#[derive(Serialize)]
+pub struct ParseReport<'a> {
+    pub input: &'a str,
+    pub ok: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub span: Option<(usize, usize)>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub diagnostics: Vec<Diagnostic>,
+    #[serde(flatten)]
+    pub result: &'a ParseResult,
+}
+
+pub fn serialize_hex<S: Serializer>(hex: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&hex::encode_upper(hex))
+}
+
+impl Encoding {
+    pub fn new(decoder: &Decoder, state: impl Fn(&Instrument) -> FieldState) -> Self {
+        let layout: &Layout = decoder.layout();
+        Self {
+            bin: decoder.bin().iter().map(|bit| bit.to_string()).collect(),
+            fields: decoder
+                .instruments()
+                .into_iter()
+                .filter_map(|instrument| {
+                    let position = layout.position(&instrument)?;
+                    Some(Field {
+                        name: instrument.name(),
+                        begin: position.begin,
+                        width: position.width as usize,
+                        value: instrument.value(),
+                        bits: format!(
+                            "{:0width$b}",
+                            instrument.value(),
+                            width = position.width as usize
+                        ),
+                        meaning: Decoder::describe(&instrument),
+                        state: state(&instrument),
+                        instrument,
+                    })
+                })
+                .collect(),
+            hex: decoder.hex(),
+            lints: vec![],
+        }
+    }
+
+    pub fn from_parser(parser: &ExprParser) -> Self {
+        Self {
+            lints: parser.lints().to_vec(),
+            ..Self::new(
+                &Decoder::with_layout(&parser.hex(), parser.layout().clone()),
+                |instrument| parser.field_state(instrument),
+            )
+        }
+    }
+
+    /// 解码得到的微指令所有字段都视为已指定
+    pub fn from_decoder(decoder: &Decoder) -> Self {
+        Self::new(decoder, |_| FieldState::Set)
+    }
+}
+
+impl ParseResult {
+    pub fn parse(input: &str) -> Self {
+        Self::parse_with_labels(input, &HashMap::new())
+    }
+
+    /// labels为NEXT=标号使用的地址，按当前使用的微指令格式编码
+    pub fn parse_with_labels(input: &str, labels: &HashMap<String, u16>) -> Self {
+        Self::parse_with_layout(input, labels, Layout::active())
+    }
+
+    pub fn parse_with_layout(
+        input: &str,
+        labels: &HashMap<String, u16>,
+        layout: Arc<Layout>,
+    ) -> Self {
+        match grammar::ExprParser::new().parse(input) {
+            Ok(expr) => {
+                let mut parser = ExprParser::with_layout(expr, labels.clone(), layout);
+                match parser.parse() {
+                    Ok(_) => ParseResult::Result(Encoding::from_parser(&parser)),
+                    Err(error) => ParseResult::ExprParseError { error },
+                }
+            }
+            Err(error) => error.into(),
+        }
+    }
+
+    pub fn is_ok(&self) -> bool {
+        matches!(self, ParseResult::Result(_))
+    }
+
+    /// 出错的位置
+    pub fn span(&self) -> Option<(usize, usize)> {
+        match self {
+            ParseResult::InvalidToken { location }
+            | ParseResult::UnrecognizedEof { location, .. } => Some((*location, *location)),
+            ParseResult::UnrecognizedToken { token, .. } => Some((token.0, token.2)),
+            ParseResult::ExprParseError { error } => error.span(),
+            ParseResult::Result(_) => None,
+        }
+    }
+
+    /// 出错时为一条错误，否则为解析中发现的警告和提示
+    pub fn diagnostics(&self, input: &str) -> Vec<Diagnostic> {
+        match self {
+            ParseResult::Result(encoding) => encoding
+                .lints
+                .iter()
+                .map(|lint| lint.diagnostic(input))
+                .collect(),
+            _ => vec![self.error(self.span().unwrap_or((0, input.len())))],
+        }
+    }
+
+    fn error(&self, span: (usize, usize)) -> Diagnostic {
+        Diagnostic {
+            severity: Severity::Error,
+            message: self.to_string(),
+            span,
+            lint: None,
+        }
+    }
+
+    pub fn format(&self, output_mode: &OutputMode) -> String {
+        match self {
+            ParseResult::Result(encoding) => output_mode.format(&encoding.hex),
+            _ => self.to_string(),
+        }
+    }
+
+    pub fn report<'a>(&'a self, input: &'a str) -> ParseReport<'a> {
+        ParseReport {
+            input,
+            ok: self.is_ok(),
+            message: (!self.is_ok()).then(|| self.to_string()),
+            span: match self {
+                ParseResult::ExprParseError { error } => error.span().or(Some((0, input.len()))),
+                _ => self.span(),
+            },
+            diagnostics: self.diagnostics(input),
+            result: self,
+        }
+    }
+}
+
+impl<T: Display> From<ParseError<usize, T, Error>> for ParseResult {
+    fn from(error: ParseError<usize, T, Error>) -> Self {
+        match error {
+            ParseError::InvalidToken { location } => ParseResult::InvalidToken { location },
+            ParseError::UnrecognizedEof { location, expected } => {
+                ParseResult::UnrecognizedEof { location, expected }
+            }
+            ParseError::UnrecognizedToken { token, expected } => ParseResult::UnrecognizedToken {
+                token: (token.0, token.1.to_string(), token.2),
+                expected,
+            },
+            ParseError::ExtraToken { token } => ParseResult::UnrecognizedToken {
+                token: (token.0, token.1.to_string(), token.2),
+                expected: vec![],
+            },
+            ParseError::User { error } => ParseResult::ExprParseError { error },
+        }
+    }
+}
+
+impl Display for ParseResult {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ParseResult::InvalidToken { location } => {
+                write!(f, "在 \" {} \" 上出现不合法的token", location)
+            }
+            ParseResult::UnrecognizedEof { location, expected } => {
+                write!(
+                    f,
+                    "期望在 \" {} \" 获得Token: [{}]",
+                    location,
+                    expected.join(", ")
+                )
+            }
+            ParseResult::UnrecognizedToken { token, expected } => {
+                write!(
+                    f,
+                    "期望获得Token: [{}], 但是获取到 {}({}, {})",
+                    expected.join(", "),
+                    token.1,
+                    token.0,
+                    token.2
+                )
+            }
+            ParseResult::ExprParseError { error } => match error {
+                Error::CanNotBeAchieved(reason) => {
+                    write!(f, "表达式解析错误: {}", reason)
+                }
+                error => write!(f, "表达式解析错误: {}", error),
+            },
+            ParseResult::Result(encoding) => {
+                write!(f, "{}", hex::encode_upper(&encoding.hex))
+            }
+        }
+    }
+}
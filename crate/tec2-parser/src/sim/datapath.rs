@@ -0,0 +1,173 @@
+use crate::parser::decoder::Decoder;
+use crate::parser::instrument::Instrument;
+use serde::Serialize;
+
+/// 标志位寄存器
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct Flags {
+    pub c: bool, // 进位
+    pub z: bool, // 结果为0
+    pub v: bool, // 溢出
+    pub s: bool, // 符号
+}
+
+/// 四片Am2901级联成的16位运算器
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct Datapath {
+    pub registers: [u16; 16],
+    pub q: u16,
+    pub flags: Flags,
+    pub ir: u16, // SA/SB为1时A口/B口取IR3-0/IR7-4
+}
+
+/// 一条微指令在运算器上的结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct AluOutput {
+    pub a: u16, // A口读出的寄存器
+    pub b: u16, // B口读出的寄存器
+    pub r: u16,
+    pub s: u16,
+    pub f: u16,
+    pub y: u16,       // 输出到总线上的值
+    pub flags: Flags, // ALU本次产生的标志位
+}
+
+impl Flags {
+    /// 恢复现场时从数据总线的高4位依次取C、Z、V、S
+    pub fn from_bits(value: u16) -> Self {
+        Self {
+            c: value & 0x8000 != 0,
+            z: value & 0x4000 != 0,
+            v: value & 0x2000 != 0,
+            s: value & 0x1000 != 0,
+        }
+    }
+
+    pub fn bits(&self) -> u16 {
+        (self.c as u16) << 15
+            | (self.z as u16) << 14
+            | (self.v as u16) << 13
+            | (self.s as u16) << 12
+    }
+}
+
+impl Datapath {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 执行一条微指令后的状态，d为数据总线上的输入(MI2-0选择D时使用)
+    pub fn step(&self, word: &Decoder, d: u16) -> (Self, AluOutput) {
+        let mut next = self.clone();
+        let output = next.execute(word, d);
+        (next, output)
+    }
+
+    /// 执行一条微指令，直接修改状态
+    pub fn execute(&mut self, word: &Decoder, d: u16) -> AluOutput {
+        let field = |instrument: Instrument| word.instrument(&instrument).value();
+        let a_address = match field(Instrument::SA([0])) {
+            0 => field(Instrument::A([0; 4])),
+            _ => self.ir & 0xF,
+        } as usize;
+        let b_address = match field(Instrument::SB([0])) {
+            0 => field(Instrument::B([0; 4])),
+            _ => (self.ir >> 4) & 0xF,
+        } as usize;
+        let (a, b, q) = (self.registers[a_address], self.registers[b_address], self.q);
+
+        let (r, s) = match field(Instrument::MI20([0; 3])) {
+            0 => (a, q),
+            1 => (a, b),
+            2 => (0, q),
+            3 => (0, b),
+            4 => (0, a),
+            5 => (d, a),
+            6 => (d, q),
+            _ => (d, 0),
+        };
+        let carry_in = match field(Instrument::SCi([0; 2])) {
+            1 => 1,
+            2 => self.flags.c as u32,
+            _ => 0,
+        };
+        // 减法按Am2901的约定为取反加Cn，SCi为0时结果比差小1
+        let (f, c, v) = match field(Instrument::MI53([0; 3])) {
+            0 => add(r, s, carry_in),
+            1 => add(!r, s, carry_in),
+            2 => add(r, !s, carry_in),
+            3 => (r | s, false, false),
+            4 => (r & s, false, false),
+            5 => (!r & s, false, false),
+            6 => (r ^ s, false, false),
+            _ => (!(r ^ s), false, false),
+        };
+        let alu_flags = Flags {
+            c,
+            z: f == 0,
+            v,
+            s: f & 0x8000 != 0,
+        };
+
+        let ssh = field(Instrument::SSH([0; 2]));
+        let carry = self.flags.c as u16;
+        // 右移时移入最高位的值，rotate为循环移位时移入的位
+        let right_in = |value: u16, rotate: u16| match ssh {
+            0 => 0,
+            1 => carry,
+            2 => rotate,
+            _ => value >> 15,
+        };
+        // 左移时移入最低位的值
+        let left_in = |rotate: u16| match ssh {
+            1 => carry,
+            2 => rotate,
+            _ => 0,
+        };
+        let mi86 = field(Instrument::MI86([0; 3]));
+        match mi86 {
+            0 => self.q = f,
+            1 => {}
+            2 | 3 => self.registers[b_address] = f,
+            4 => {
+                self.registers[b_address] = right_in(f, q & 1) << 15 | f >> 1;
+                self.q = (f & 1) << 15 | q >> 1;
+            }
+            5 => self.registers[b_address] = right_in(f, f & 1) << 15 | f >> 1,
+            6 => {
+                self.registers[b_address] = f << 1 | q >> 15;
+                self.q = q << 1 | left_in(f >> 15);
+            }
+            _ => self.registers[b_address] = f << 1 | left_in(f >> 15),
+        }
+        let y = if mi86 == 2 { a } else { f };
+
+        match field(Instrument::SST([0; 3])) {
+            0 => {}
+            1 => self.flags = alu_flags,
+            2 => self.flags = Flags::from_bits(d),
+            3 => self.flags.c = false,
+            4 => self.flags.c = true,
+            5 => self.flags.c = f & 1 != 0,
+            6 => self.flags.c = f & 0x8000 != 0,
+            _ => self.flags.c = q & 1 != 0,
+        }
+
+        AluOutput {
+            a,
+            b,
+            r,
+            s,
+            f,
+            y,
+            flags: alu_flags,
+        }
+    }
+}
+
+/// 带进位的16位加法，返回(结果, 进位, 溢出)
+fn add(x: u16, y: u16, carry_in: u32) -> (u16, bool, bool) {
+    let sum = x as u32 + y as u32 + carry_in;
+    let f = sum as u16;
+    (f, sum > 0xFFFF, (x ^ f) & (y ^ f) & 0x8000 != 0)
+}
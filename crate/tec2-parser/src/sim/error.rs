@@ -0,0 +1,26 @@
+use serde::Serialize;
+use std::fmt::{Display, Formatter};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", content = "detail", rename_all = "snake_case")]
+pub enum SimError {
+    EmptyAddress(u16),  // 控存中没有该地址的微指令
+    UnmappedOpcode(u8), // JMAP时映射表中没有该操作码
+    StackUnderflow(u16),
+}
+
+impl Display for SimError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SimError::EmptyAddress(address) => {
+                write!(f, "控存地址 {:03X}H 上没有微指令", address)
+            }
+            SimError::UnmappedOpcode(opcode) => {
+                write!(f, "操作码 {:02X}H 没有对应的微程序入口", opcode)
+            }
+            SimError::StackUnderflow(address) => {
+                write!(f, "地址 {:03X}H 的微指令从空的微堆栈中出栈", address)
+            }
+        }
+    }
+}
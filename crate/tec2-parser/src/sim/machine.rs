@@ -0,0 +1,139 @@
+use crate::parser::decoder::Decoder;
+use crate::parser::instrument::{Ci, DC2, Instrument, MEM};
+use crate::parser::layout::Layout;
+use crate::sim::{Datapath, Sequencer, Signals, SimError};
+use serde::Serialize;
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::Arc;
+
+/// 主存的字数
+pub const MEMORY_SIZE: usize = 1 << 16;
+
+/// 通用寄存器中PC和IP的位置
+pub const PC: usize = 5;
+pub const IP: usize = 6;
+
+/// 由控存驱动的整机，主存64K字
+///
+/// 每条微指令按以下顺序执行：按AR读主存或I/O，微程序控制器用执行前的标志位选择下地址，
+/// 运算器计算，按AR写主存或I/O，最后由DC2把总线上的值打入IR或者把Y打入AR
+#[derive(Debug, Clone, Serialize)]
+pub struct Machine {
+    #[serde(skip)]
+    pub memory: Vec<u16>,
+    pub ar: u16,
+    pub datapath: Datapath,
+    pub sequencer: Sequencer,
+    pub signals: Signals,
+    #[serde(skip)]
+    pub input: VecDeque<u16>, // I/O读依次取出的值
+    pub output: Vec<(u16, u16)>, // I/O写的(端口, 值)
+    pub cycles: u64,
+    #[serde(skip)]
+    store: BTreeMap<u16, Vec<u8>>,
+}
+
+impl Machine {
+    /// store为控存，map为JMAP使用的操作码到微程序入口的映射，按当前使用的微指令格式解码
+    pub fn new(store: BTreeMap<u16, Vec<u8>>, map: BTreeMap<u8, u16>) -> Self {
+        Self::with_layout(store, map, Layout::active())
+    }
+
+    pub fn with_layout(
+        store: BTreeMap<u16, Vec<u8>>,
+        map: BTreeMap<u8, u16>,
+        layout: Arc<Layout>,
+    ) -> Self {
+        Self {
+            memory: vec![0; MEMORY_SIZE],
+            ar: 0,
+            datapath: Datapath::new(),
+            sequencer: Sequencer::with_layout(0, layout).with_map(map),
+            signals: Signals::default(),
+            input: VecDeque::new(),
+            output: vec![],
+            cycles: 0,
+            store,
+        }
+    }
+
+    /// 把程序写入主存
+    pub fn load(&mut self, address: u16, words: &[u16]) {
+        for (i, word) in words.iter().enumerate() {
+            self.memory[address.wrapping_add(i as u16) as usize] = *word;
+        }
+    }
+
+    pub fn pc(&self) -> u16 {
+        self.datapath.registers[PC]
+    }
+
+    pub fn set_pc(&mut self, pc: u16) {
+        self.datapath.registers[PC] = pc;
+    }
+
+    pub fn store(&self) -> &BTreeMap<u16, Vec<u8>> {
+        &self.store
+    }
+
+    /// 执行一条微指令，返回执行的微指令
+    pub fn cycle(&mut self) -> Result<Decoder, SimError> {
+        let address = self.sequencer.address;
+        let word = Decoder::with_layout(
+            self.store
+                .get(&address)
+                .ok_or(SimError::EmptyAddress(address))?,
+            self.sequencer.layout().clone(),
+        );
+        let mem = word.mem();
+        let d = match mem {
+            MEM::MemRead => self.memory[self.ar as usize],
+            MEM::IoRead => self.input.pop_front().unwrap_or(0),
+            _ => 0,
+        };
+        self.sequencer.step(&word, &self.datapath, &self.signals)?;
+        let output = self.datapath.execute(&word, d);
+        let bus = match mem {
+            MEM::MemRead | MEM::IoRead => d,
+            _ => output.y,
+        };
+        match mem {
+            MEM::MemWrite => self.memory[self.ar as usize] = output.y,
+            MEM::IoWrite => self.output.push((self.ar, output.y)),
+            _ => {}
+        }
+        match DC2::try_from(word.instrument(&Instrument::DC2([0; 3])).value() as u64) {
+            Ok(DC2::IR) => self.datapath.ir = bus,
+            Ok(DC2::AR) => self.ar = output.y,
+            _ => {}
+        }
+        self.cycles += 1;
+        Ok(word)
+    }
+
+    /// 执行到下一次JMAP，即完成一条机器指令的取指和分派
+    pub fn step(&mut self, max_cycles: u64) -> Result<u64, SimError> {
+        let jmap = Ci::JMAP as u16;
+        for cycles in 1..=max_cycles {
+            if self.cycle()?.instrument(&Instrument::CI([0; 4])).value() == jmap {
+                return Ok(cycles);
+            }
+        }
+        Ok(max_cycles)
+    }
+
+    /// 最多执行max_cycles条微指令，stop返回true时停止，返回执行的条数
+    pub fn run(
+        &mut self,
+        max_cycles: u64,
+        mut stop: impl FnMut(&Machine) -> bool,
+    ) -> Result<u64, SimError> {
+        for cycles in 0..max_cycles {
+            if stop(self) {
+                return Ok(cycles);
+            }
+            self.cycle()?;
+        }
+        Ok(max_cycles)
+    }
+}
@@ -0,0 +1,9 @@
+mod datapath;
+mod error;
+mod machine;
+mod sequencer;
+
+pub use datapath::*;
+pub use error::*;
+pub use machine::*;
+pub use sequencer::*;
@@ -0,0 +1,252 @@
+use crate::ast::token::Condition;
+use crate::parser::decoder::Decoder;
+use crate::parser::instrument::{Ci, Instrument};
+use crate::parser::layout::Layout;
+use crate::sim::{Datapath, SimError};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+/// 微堆栈的深度
+pub const STACK_DEPTH: usize = 5;
+
+/// 条件选择中来自机器外部的信号
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct Signals {
+    pub fs1: bool,
+    pub fs2: bool,
+    pub fs3: bool,
+    pub wait: bool,
+    pub int: bool, // 有中断请求
+}
+
+/// Am2910微程序控制器
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Sequencer {
+    pub address: u16, // 当前微指令的地址
+    pub upc: u16,     // 微程序计数器，为当前地址加1
+    pub stack: Vec<u16>,
+    pub counter: u16,
+    pub map: BTreeMap<u8, u16>, // JMAP时由IR15-8得到微程序入口
+    #[serde(skip)]
+    layout: Arc<Layout>, // 控存的微指令格式，微地址按下地址的位数回绕
+}
+
+/// 按地址依次执行的结果
+#[derive(Debug, Clone, Serialize)]
+pub struct Trace {
+    pub addresses: Vec<u16>,
+    pub error: Option<SimError>,
+}
+
+impl Sequencer {
+    /// 按当前使用的微指令格式，见with_layout
+    pub fn new(address: u16) -> Self {
+        Self::with_layout(address, Layout::active())
+    }
+
+    pub fn with_layout(address: u16, layout: Arc<Layout>) -> Self {
+        Self {
+            address,
+            upc: address.wrapping_add(1) & layout.max_address(),
+            stack: vec![],
+            counter: 0,
+            map: BTreeMap::new(),
+            layout,
+        }
+    }
+
+    pub fn layout(&self) -> &Arc<Layout> {
+        &self.layout
+    }
+
+    /// 从address开始执行
+    pub fn jump(&mut self, address: u16) {
+        self.address = address;
+        self.upc = address.wrapping_add(1) & self.layout.max_address();
+    }
+
+    pub fn with_map(mut self, map: BTreeMap<u8, u16>) -> Self {
+        self.map = map;
+        self
+    }
+
+    /// 条件是否满足，CC#为低电平时满足
+    ///
+    /// 条件为IR10-8时和JR系列指令的编码一致：IR10为0时无条件满足，
+    /// 否则IR9选择C(0)或Z(1)，IR8为1时测试标志位为0
+    pub fn pass(condition: &Condition, datapath: &Datapath, signals: &Signals) -> bool {
+        let flags = &datapath.flags;
+        match condition {
+            Condition::Zero => true,
+            Condition::One => false,
+            Condition::NotFS1 => signals.fs1,
+            Condition::NotFS2 => signals.fs2,
+            Condition::NotFS3 => signals.fs3,
+            Condition::NotWait => signals.wait,
+            Condition::NotC => flags.c,
+            Condition::NotZ => flags.z,
+            Condition::NotV => flags.v,
+            Condition::NotS => flags.s,
+            Condition::NotINT => signals.int,
+            Condition::IR108 => {
+                let ir = datapath.ir;
+                let flag = if ir & 0x200 == 0 { flags.c } else { flags.z };
+                ir & 0x400 == 0 || flag != (ir & 0x100 != 0)
+            }
+        }
+    }
+
+    /// 执行一条微指令，返回下一条微指令的地址
+    pub fn step(
+        &mut self,
+        word: &Decoder,
+        datapath: &Datapath,
+        signals: &Signals,
+    ) -> Result<u16, SimError> {
+        let field = |instrument: Instrument| word.instrument(&instrument).value();
+        let max_address = self.layout.max_address();
+        let d = field(Instrument::NEXT([0; 10]));
+        let pass = word
+            .condition()
+            .is_some_and(|condition| Self::pass(&condition, datapath, signals));
+        let ci = Ci::try_from(field(Instrument::CI([0; 4])) as u64).unwrap_or(Ci::SEQ);
+        let counter_zero = self.counter == 0;
+        let upc = self.upc;
+        let next = match ci {
+            Ci::INIT => {
+                self.stack.clear();
+                0
+            }
+            Ci::CJS if pass => {
+                self.push(upc);
+                d
+            }
+            Ci::JMAP => {
+                let opcode = (datapath.ir >> 8) as u8;
+                *self
+                    .map
+                    .get(&opcode)
+                    .ok_or(SimError::UnmappedOpcode(opcode))?
+            }
+            Ci::IF | Ci::CJV if pass => d,
+            Ci::PUSH => {
+                self.push(upc);
+                if pass {
+                    self.counter = d;
+                }
+                upc
+            }
+            Ci::JSRP => {
+                self.push(upc);
+                if pass { d } else { self.counter }
+            }
+            Ci::JRP if pass => d,
+            Ci::JRP => self.counter,
+            Ci::RFCT if !counter_zero => {
+                self.counter -= 1;
+                self.top()?
+            }
+            Ci::RFCT => {
+                self.pop()?;
+                upc
+            }
+            Ci::RPCT if !counter_zero => {
+                self.counter -= 1;
+                d
+            }
+            Ci::CRTN if pass => self.pop()?,
+            Ci::CJPP if pass => {
+                self.pop()?;
+                d
+            }
+            Ci::LDCT => {
+                self.counter = d;
+                upc
+            }
+            Ci::LOOP if !pass => self.top()?,
+            Ci::LOOP => {
+                self.pop()?;
+                upc
+            }
+            Ci::TWB => match (pass, counter_zero) {
+                (false, false) => {
+                    self.counter -= 1;
+                    self.top()?
+                }
+                (false, true) => {
+                    self.pop()?;
+                    d
+                }
+                (true, zero) => {
+                    if !zero {
+                        self.counter -= 1;
+                    }
+                    self.pop()?;
+                    upc
+                }
+            },
+            _ => upc,
+        } & max_address;
+        self.address = next;
+        self.upc = next.wrapping_add(1) & max_address;
+        Ok(next)
+    }
+
+    /// 从当前地址开始最多执行steps条微指令，datapath用于提供标志位和IR
+    pub fn trace(
+        &mut self,
+        store: &BTreeMap<u16, Vec<u8>>,
+        datapath: &mut Datapath,
+        signals: &Signals,
+        steps: usize,
+    ) -> Trace {
+        let mut addresses = vec![self.address];
+        for _ in 0..steps {
+            let result = store
+                .get(&self.address)
+                .ok_or(SimError::EmptyAddress(self.address))
+                .and_then(|hex| {
+                    // 条件测试的是本条微指令执行前的标志位
+                    let word = Decoder::with_layout(hex, self.layout.clone());
+                    let address = self.step(&word, datapath, signals)?;
+                    datapath.execute(&word, 0);
+                    Ok(address)
+                });
+            match result {
+                Ok(address) => addresses.push(address),
+                Err(error) => {
+                    return Trace {
+                        addresses,
+                        error: Some(error),
+                    };
+                }
+            }
+        }
+        Trace {
+            addresses,
+            error: None,
+        }
+    }
+
+    /// 栈满时覆盖栈顶
+    fn push(&mut self, address: u16) {
+        if self.stack.len() == STACK_DEPTH {
+            self.stack.pop();
+        }
+        self.stack.push(address);
+    }
+
+    fn top(&self) -> Result<u16, SimError> {
+        self.stack
+            .last()
+            .copied()
+            .ok_or(SimError::StackUnderflow(self.address))
+    }
+
+    fn pop(&mut self) -> Result<u16, SimError> {
+        self.stack
+            .pop()
+            .ok_or(SimError::StackUnderflow(self.address))
+    }
+}
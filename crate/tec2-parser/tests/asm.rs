@@ -0,0 +1,153 @@
+use std::collections::BTreeMap;
+use tec2_parser::asm::{AsmErrorKind, Assembly, DefinitionError, InstructionSet};
+use tec2_parser::program::Program;
+use tec2_parser::sim::Machine;
+
+#[test]
+fn test_assemble() {
+    let assembly = Assembly::assemble(
+        "; 求和\n\
+         \x20       ORG 0100H\n\
+         START:  MVRD R1, 5\n\
+         LOOP:   ADD R2, R1\n\
+         \x20       DEC R1\n\
+         \x20       JRNZ LOOP\n\
+         \x20       STRR [SP], R2   // 保存结果\n\
+         \x20       JMPA START\n\
+         DATA:   DW 1234H, -1, DATA\n",
+    )
+    .unwrap();
+    assert_eq!(assembly.label("START"), Some(0x100));
+    assert_eq!(assembly.label("LOOP"), Some(0x102));
+    assert_eq!(assembly.label("DATA"), Some(0x108));
+    let words = assembly.image().into_values().collect::<Vec<_>>();
+    assert_eq!(
+        words,
+        vec![
+            0x8810, 0x0005, 0x0021, 0x0810, 0x47FD, 0x8342, 0x8000, 0x0100, 0x1234, 0xFFFF, 0x0108
+        ]
+    );
+    assert!(
+        assembly
+            .listing()
+            .starts_with("0100  8810 0005   START:  MVRD R1, 5\n")
+    );
+}
+
+#[test]
+fn test_assemble_error() {
+    let error = Assembly::assemble("MVRD R1, 5\nFOO R1").unwrap_err();
+    assert_eq!(error.line, 2);
+    assert!(matches!(error.kind, AsmErrorKind::UnknownMnemonic(_)));
+
+    let error = Assembly::assemble("JR FAR\nORG 200H\nFAR: RET").unwrap_err();
+    assert!(matches!(error.kind, AsmErrorKind::OffsetOutOfRange(511)));
+
+    let error = Assembly::assemble("ADD R16, R1").unwrap_err();
+    assert!(matches!(error.kind, AsmErrorKind::InvalidOperand(_)));
+
+    let error = Assembly::assemble("A: RET\nA: RET").unwrap_err();
+    assert!(matches!(error.kind, AsmErrorKind::DuplicateLabel(_)));
+
+    let error = Assembly::assemble("JMPA NOWHERE").unwrap_err();
+    assert!(matches!(error.kind, AsmErrorKind::UndefinedLabel(_)));
+
+    let error = Assembly::assemble("MVRD R1, 5\nORG 1\nRET").unwrap_err();
+    assert!(matches!(error.kind, AsmErrorKind::AddressOverlap(1)));
+}
+
+#[test]
+fn test_disassemble() {
+    let set = InstructionSet::tec2();
+    let words = [
+        0x8810, 0x0005, 0x0021, 0x47FD, 0x8342, 0xFF00, 0xCE00, 0x0100, 0x8800,
+    ];
+    let lines = set.disassemble(0x100, &words);
+    let texts = lines.iter().map(|x| x.text.as_str()).collect::<Vec<_>>();
+    assert_eq!(
+        texts,
+        vec![
+            "MVRD R1, 0005H",
+            "ADD R2, R1",
+            "JRNZ 0101H",
+            "STRR [R4], R2",
+            "DW FF00H",
+            "CALA 0100H",
+            "DW 8800H",
+        ]
+    );
+    assert!(!lines[4].known && !lines[6].known);
+    assert_eq!(set.decode(0x100, &[]), None);
+    assert!(set.disassemble(0x100, &[]).is_empty());
+
+    // 反汇编的结果可以重新汇编
+    let source = lines
+        .iter()
+        .map(|x| x.text.clone())
+        .collect::<Vec<_>>()
+        .join("\n");
+    let assembly = Assembly::assemble(&format!("ORG 100H\n{}", source)).unwrap();
+    assert_eq!(
+        assembly.image().into_values().collect::<Vec<_>>(),
+        words.to_vec()
+    );
+}
+
+#[test]
+fn test_instruction_definition() {
+    let set = InstructionSet::from_toml(
+        r#"
+        [[instructions]]
+        mnemonic = "MVRD"
+        opcode = 0x88
+        format = "dr_immediate"
+        entry = "MVRD"
+
+        [[instructions]]
+        mnemonic = "ADD3"
+        opcode = 0xE0
+        format = "dr_sr"
+        entry = "ADD3"
+        "#,
+    )
+    .unwrap();
+    assert_eq!(set.find("add3").unwrap().opcode, 0xE0);
+    assert_eq!(set.find("ADD").unwrap().opcode, 0x00);
+
+    let error = InstructionSet::from_toml(
+        "base = false\n\
+         [[instructions]]\nmnemonic = \"A\"\nopcode = 1\nformat = \"none\"\n\
+         [[instructions]]\nmnemonic = \"B\"\nopcode = 1\nformat = \"none\"\n",
+    )
+    .unwrap_err();
+    assert!(matches!(error, DefinitionError::DuplicateOpcode(1)));
+
+    // 新指令ADD3 DR, SR: DR + SR + SR -> DR
+    let program = Program::parse(
+        "FETCH: PC -> AR, PC + 1 -> PC\n\
+         \x20      MEM -> IR\n\
+         \x20      , CI=JMAP\n\
+         MVRD:  PC -> AR, PC + 1 -> PC\n\
+         \x20      MEM -> DR, NEXT=FETCH, CC#=0\n\
+         ADD3:  SR + DR -> DR\n\
+         \x20      SR + DR -> DR, NEXT=FETCH, CC#=0\n",
+    )
+    .unwrap();
+    let map = set.map(&program).unwrap();
+    assert_eq!(map, BTreeMap::from([(0x88, 3), (0xE0, 5)]));
+    let assembly = Assembly::assemble_with("MVRD R1, 2\nMVRD R2, 1\nADD3 R2, R1", &set).unwrap();
+    let mut machine = Machine::new(program.image().unwrap(), map);
+    machine.load(0, &assembly.image().into_values().collect::<Vec<_>>());
+    machine
+        .run(100, |machine| {
+            machine.pc() == 5 && machine.sequencer.address == 0
+        })
+        .unwrap();
+    assert_eq!(machine.datapath.registers[2], 5);
+
+    let program = Program::parse("FETCH: PC -> AR").unwrap();
+    assert!(matches!(
+        set.map(&program).unwrap_err(),
+        DefinitionError::UndefinedEntry(..)
+    ));
+}
@@ -0,0 +1,98 @@
+use tec2_parser::ast::token::Identifier;
+use tec2_parser::compile::compile;
+use tec2_parser::parser::decoder::Decoder;
+use tec2_parser::program::Program;
+use tec2_parser::result::ParseResult;
+use tec2_parser::sim::Datapath;
+use tec2_parser::{CanNotBeAchievedReason, Error};
+
+fn sources(input: &str, scratch: &[Identifier]) -> Vec<String> {
+    let steps = compile(input, scratch).unwrap_or_else(|result| panic!("{}", result));
+    steps.into_iter().map(|step| step.source).collect()
+}
+
+#[test]
+fn test_compile() {
+    let scratch = [Identifier::Q];
+    assert_eq!(
+        sources("PC -> AR, PC + 1 -> PC", &scratch),
+        ["PC -> AR, PC + 1 -> PC"]
+    );
+    assert_eq!(
+        sources("R1 + R2 + R3 -> R4", &scratch),
+        ["R1 + R2 -> Q", "Q + R3 -> R4"]
+    );
+    assert_eq!(sources("MEM -> MEM", &scratch), ["MEM -> Q", "Q -> MEM"]);
+    assert_eq!(sources("MEM -> MEM", &[]), ["MEM -> Q", "Q -> MEM"]);
+    assert_eq!(
+        sources("DR + MEM -> Q, NEXT=5, CC#=0", &scratch),
+        ["DR -> Q", "Q + MEM -> Q, NEXT=5, CC#=0"]
+    );
+    // 标志按语法树中的位置取出
+    assert_eq!(
+        sources("R1 + R2 + R3 -> R4 ,NEXT=5,  CC#=0", &scratch),
+        ["R1 + R2 -> Q", "Q + R3 -> R4,NEXT=5,  CC#=0"]
+    );
+    // 标志和最后一条冲突时单独一条
+    assert_eq!(
+        sources("SR -> R2, PC + 1 -> PC", &scratch),
+        ["SR -> R2", ", PC + 1 -> PC"]
+    );
+    // 依次尝试，取最少的，后面还要读取的操作数不能暂存
+    assert_eq!(
+        sources("R1 + Q + R2 -> AR", &scratch),
+        ["R1 + Q -> Q", "Q + R2 -> AR"]
+    );
+    assert_eq!(
+        sources("R1 + R2 + Q -> AR", &[Identifier::Q, Identifier::R(10)]),
+        ["R1 -> R10", "R10 + R2 -> R10", "R10 + Q -> AR"]
+    );
+    assert_eq!(sources("PC + Q -> AR", &scratch), ["R5 + Q -> AR"]);
+    assert!(matches!(
+        compile("R1 + R2 + Q -> AR", &scratch),
+        Err(ParseResult::ExprParseError {
+            error: Error::CanNotBeAchieved(CanNotBeAchievedReason::UnknownExpr)
+        })
+    ));
+    // 目的寄存器也可以暂存
+    assert_eq!(
+        sources("R1 + Q + R2 -> R3", &scratch),
+        ["R1 + Q -> R3", "R3 + R2 -> R3"]
+    );
+    assert!(matches!(
+        compile("MEM -> MEM", &[Identifier::AR]),
+        Err(ParseResult::ExprParseError {
+            error: Error::InvalidScratch(_)
+        })
+    ));
+
+    let mut state = Datapath::new();
+    state.registers[1] = 1;
+    state.registers[2] = 2;
+    state.registers[3] = 4;
+    for step in compile("R1 + R2 + R3 -> R4", &scratch).unwrap() {
+        state = state.step(&Decoder::new(&step.encoding.hex), 0).0;
+    }
+    assert_eq!(state.registers[4], 7);
+}
+
+#[test]
+fn test_program_compile() {
+    let source = "START: R1 + R2 + R3 -> R4\nMEM -> MEM, NEXT=START, CC#=0\nEND: Q -> AR";
+    let program = Program::compile(source, &[Identifier::Q]).unwrap();
+    let words = program
+        .instructions()
+        .iter()
+        .map(|instruction| (instruction.address, instruction.line))
+        .collect::<Vec<_>>();
+    assert_eq!(words, [(0, 1), (1, 1), (2, 2), (3, 2), (4, 3)]);
+    assert_eq!(program.label("END"), Some(4));
+    assert_eq!(
+        program.instructions()[3].source,
+        "Q -> MEM, NEXT=START, CC#=0"
+    );
+    // 默认用Q暂存
+    let parsed = Program::parse(source).unwrap();
+    assert_eq!(parsed.instructions().len(), 5);
+    assert_eq!(parsed.image().unwrap(), program.image().unwrap());
+}
@@ -0,0 +1,39 @@
+use tec2_parser::Error;
+use tec2_parser::parser::instrument::{Instrument, Mi86, SCi, ToInstrument};
+
+/// 自定义的字段枚举
+#[derive(Debug, PartialEq, ToInstrument)]
+#[field(SSH, width = 2)]
+enum Shift {
+    /// 不移位
+    None,
+    /// 逻辑左移
+    Left = 2,
+    Right,
+}
+
+#[test]
+fn test_derive() {
+    assert_eq!(
+        Mi86::FBA.to_instrument().to_vec(),
+        vec![Instrument::MI86([0, 1, 0])]
+    );
+    assert!(matches!(Mi86::try_from(4u64), Ok(Mi86::F2BQ2QF)));
+    assert_eq!(Mi86::FBA.to_string(), "F->B, Y=A");
+    assert!(matches!(
+        SCi::try_from(3u64),
+        Err(Error::InvalidFieldValue {
+            field: "SCi",
+            value: 3
+        })
+    ));
+
+    assert_eq!(
+        Shift::Right.to_instrument().to_vec(),
+        vec![Instrument::SSH([1, 1])]
+    );
+    assert!(matches!(Shift::try_from(0u64), Ok(Shift::None)));
+    assert!(Shift::try_from(1u64).is_err());
+    assert_eq!(Shift::Left.to_string(), "逻辑左移");
+    assert_eq!(Shift::Right.to_string(), "Right");
+}
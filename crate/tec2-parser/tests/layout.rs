@@ -0,0 +1,106 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use tec2_parser::Error;
+use tec2_parser::grammar;
+use tec2_parser::parser::decoder::Decoder;
+use tec2_parser::parser::format::WordFormat;
+use tec2_parser::parser::instrument::{Instrument, Mi86};
+use tec2_parser::parser::layout::{Layout, LayoutError};
+use tec2_parser::parser::parser::ExprParser;
+
+/// 8位下地址、去掉SCi/SSH和DC2的48位格式
+const BOARD: &str = r#"
+name = "board"
+width = 48
+
+[[fields]]
+field = "NEXT"
+begin = 0
+width = 8
+
+[[fields]]
+field = "CI"
+begin = 8
+width = 4
+"#;
+
+fn parse(input: &str, layout: &Arc<Layout>) -> Result<ExprParser, Error> {
+    let expr = grammar::ExprParser::new().parse(input).unwrap();
+    let mut parser = ExprParser::with_layout(expr, HashMap::new(), layout.clone());
+    parser.parse().map(|_| parser)
+}
+
+#[test]
+fn test_validate() {
+    assert_eq!(
+        Layout::from_json(&serde_json::to_string(&Layout::tec2()).unwrap()).unwrap(),
+        Layout::tec2()
+    );
+    let overlap = BOARD.replace("begin = 8", "begin = 6");
+    assert!(matches!(
+        Layout::from_toml(&overlap),
+        Err(LayoutError::FieldOverlap(..))
+    ));
+    let out_of_range = BOARD.replace("begin = 8", "begin = 46");
+    assert!(matches!(
+        Layout::from_toml(&out_of_range),
+        Err(LayoutError::FieldOutOfRange(_))
+    ));
+    assert!(matches!(
+        Layout::from_toml(&BOARD.replace("width = 48", "width = 50")),
+        Err(LayoutError::InvalidWidth(50))
+    ));
+    assert!(matches!(
+        Layout::from_toml(&BOARD.replace("width = 8", "width = 12")),
+        Err(LayoutError::InvalidFieldWidth(..))
+    ));
+    assert!(matches!(
+        Layout::from_toml(&BOARD.replace("\"CI\"", "\"下地址\"")),
+        Err(LayoutError::DuplicateField(_))
+    ));
+}
+
+#[test]
+fn test_custom_layout() {
+    let mut layout = Layout::from_toml(BOARD).unwrap();
+    for (field, begin) in [
+        ("MI86", 12),
+        ("MI53", 15),
+        ("MI20", 18),
+        ("A", 21),
+        ("B", 25),
+    ] {
+        layout
+            .fields
+            .push(tec2_parser::parser::layout::FieldLayout {
+                field: field.to_string(),
+                begin,
+                width: Instrument::from_key(field).unwrap().length() as u8,
+            });
+    }
+    let layout = Arc::new(layout);
+
+    let parser = parse(", NEXT=0xFF", &layout).unwrap();
+    assert_eq!(hex::encode_upper(parser.hex()), "FFE200000000");
+    assert!(matches!(
+        parse(", NEXT=0x100", &layout),
+        Err(Error::AddressOutOfRange(0x100))
+    ));
+    assert!(matches!(
+        parse("SR -> AR", &layout),
+        Err(Error::FieldDoesNotFit { .. })
+    ));
+
+    let from_hex = |hex: &str| Decoder::from_words(hex, &WordFormat::default(), layout.clone());
+    let decoder = from_hex("FFE200000000").unwrap();
+    assert_eq!(decoder.instrument(&Instrument::NEXT([0; 10])).value(), 0xFF);
+    assert_eq!(
+        decoder.instrument(&Instrument::MI86([0; 3])).value(),
+        Mi86::NONE as u16
+    );
+    assert_eq!(decoder.instruments().len(), 7);
+    assert!(from_hex("FF0E0090400082").is_err());
+    // 当前使用的格式不受影响
+    assert!(Decoder::from_hex("FF0E0090400082").is_ok());
+    assert!(parse(", NEXT=0x100", &Layout::active()).is_ok());
+}
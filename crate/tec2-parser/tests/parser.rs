@@ -1,4 +1,5 @@
 use tec2_parser::grammar;
+use tec2_parser::parser::format::{OutputMode, Padding, WordFormat, WordOrder};
 use tec2_parser::parser::parser::ExprParser;
 
 fn get_hex(input: &str) -> String {
@@ -7,6 +8,12 @@ fn get_hex(input: &str) -> String {
     hex::encode(parser.hex()).to_uppercase()
 }
 
+fn get_words(input: &str, padding: Padding, order: WordOrder) -> String {
+    let mut parser = ExprParser::new(grammar::ExprParser::new().parse(input).unwrap());
+    parser.parse().unwrap();
+    OutputMode::Words(WordFormat::new(padding, order)).format(&parser.hex())
+}
+
 #[test]
 fn test_1() {
     assert_eq!(get_hex(", PC + 1 -> PC"), "000E00A0305400");
@@ -24,11 +31,32 @@ fn test_1() {
     assert_eq!(get_hex("MEM + Q -> AR"), "000E0010E00002");
     assert_eq!(get_hex("MEM + Q -> Q"), "000E0000E00000");
     assert_eq!(get_hex("MEM + Q -> Q, CarryFromALU"), "000E0100E00000");
-    assert_eq!(get_hex("MEM - Q -> Q"), "000E0002E00000");
-    assert_eq!(get_hex("MEM - Q -> Q, CarryFromALU"), "000E0102E00000");
+    assert_eq!(get_hex("MEM - Q -> Q"), "000E0002E00400");
+    assert_eq!(get_hex("MEM - Q -> Q, CarryFromALU"), "000E0102E00400");
     assert_eq!(get_hex("DR -> AR"), "000E009030000A"); // 待更新000E009030008A
     assert_eq!(get_hex("Q -> MEM, CC#=0"), "29030010200010");
-    assert_eq!(get_hex("SR - DR -> Q, CarryFromALU"), "000E0182100088"); // 待更新000E0192100088
+    assert_eq!(get_hex("SR - DR -> Q, CarryFromALU"), "000E0182100488"); // 待更新000E0192100088
     assert_eq!(get_hex("PC -> AR , PC + 1 -> PC, CC#=Z"), "2903E0A0355402");
     assert_eq!(get_hex("IP + MEM -> PC, CC#=0"), "29030030D65000");
 }
+
+#[test]
+fn test_words() {
+    let input = "SR -> AR";
+    assert_eq!(
+        get_words(input, Padding::Left, WordOrder::HighFirst),
+        "0000 0E00 9040 0082"
+    );
+    assert_eq!(
+        get_words(input, Padding::Left, WordOrder::LowFirst),
+        "0082 9040 0E00 0000"
+    );
+    assert_eq!(
+        get_words(input, Padding::Right, WordOrder::HighFirst),
+        "000E 0090 4000 8200"
+    );
+    assert_eq!(
+        get_words(input, Padding::Right, WordOrder::LowFirst),
+        "8200 4000 0090 000E"
+    );
+}
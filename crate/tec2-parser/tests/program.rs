@@ -0,0 +1,282 @@
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use tec2_parser::parser::decoder::Decoder;
+use tec2_parser::parser::format::{Padding, WordFormat, WordOrder};
+use tec2_parser::parser::instrument::{Instrument, MEM};
+use tec2_parser::parser::layout::Layout;
+use tec2_parser::program::{
+    ControlFlow, EdgeKind, ListingFormat, Program, ProgramErrorKind, WarningKind,
+};
+
+fn get_image(input: &str) -> Vec<(u16, String)> {
+    Program::parse(input)
+        .unwrap()
+        .image()
+        .unwrap()
+        .into_iter()
+        .map(|(address, hex)| (address, hex::encode_upper(hex)))
+        .collect()
+}
+
+#[test]
+fn test_build() {
+    let image = get_image(
+        "; 取指\n\
+         ORG 0A0H\n\
+         FETCH: PC -> AR, PC + 1 -> PC\n\
+         \x20      MEM -> DR, NEXT=FETCH, CC#=0 // 跳回\n\
+         END:\n\
+         \x20      SR -> AR, NEXT=0x10, CC#=/Z\n",
+    );
+    assert_eq!(
+        image,
+        vec![
+            (0xA0, "000E00A0355402".to_string()),
+            (0xA1, "28030030F00008".to_string()),
+            (0xA2, "04037090400082".to_string()),
+        ]
+    );
+}
+
+#[test]
+fn test_build_error() {
+    let error = Program::parse("SR -> AR, NEXT=NOWHERE").unwrap_err();
+    assert_eq!(error.line, 1);
+    assert!(matches!(error.kind, ProgramErrorKind::Expr(_)));
+
+    let error = Program::parse("A: SR -> AR\nA: Q -> AR").unwrap_err();
+    assert_eq!(error.line, 2);
+    assert!(matches!(error.kind, ProgramErrorKind::DuplicateLabel(_)));
+
+    let error = Program::parse("SR -> AR\nORG 0\nQ -> AR")
+        .unwrap()
+        .image()
+        .unwrap_err();
+    assert_eq!(error.line, 3);
+    assert!(matches!(error.kind, ProgramErrorKind::AddressOverlap(0)));
+
+    // ORG超出控存时在ORG这一行报错
+    let error = Program::parse("ORG 4294967295\nMEM -> AR").unwrap_err();
+    assert_eq!(error.line, 1);
+    assert!(matches!(
+        error.kind,
+        ProgramErrorKind::AddressOutOfRange(4294967295)
+    ));
+    let error = Program::parse("ORG 3FFH\nSR -> AR\nQ -> AR").unwrap_err();
+    assert_eq!(error.line, 3);
+    assert!(matches!(
+        error.kind,
+        ProgramErrorKind::AddressOutOfRange(0x400)
+    ));
+
+    // 文件末尾的标号和NEXT=使用的地址一致
+    let program = Program::parse("ORG 10H\nSR -> AR, NEXT=END\nEND:").unwrap();
+    assert_eq!(program.label("END"), Some(0x11));
+    assert_eq!(program.instructions()[0].encoding.hex[0..2], [0x04, 0x4E]);
+    // 控存已满时文件末尾的标号没有地址可以指向
+    let error = Program::parse("ORG 3FFH\nSR -> AR\nEND:").unwrap_err();
+    assert_eq!(error.line, 3);
+    assert!(matches!(
+        error.kind,
+        ProgramErrorKind::AddressOutOfRange(0x400)
+    ));
+
+    // NEXT=和ORG一样支持H后缀
+    let program = Program::parse("ORG 0A0H\nSR -> AR, NEXT=0A0H\n, NEXT=1").unwrap();
+    assert_eq!(program.instructions()[0].encoding.hex[0..2], [0x28, 0x0E]);
+    assert_eq!(program.instructions()[1].encoding.hex[0..2], [0x00, 0x4E]);
+
+    // 和寄存器名、关键字相同的标号在NEXT=中无法使用
+    for label in ["C", "S", "R1", "PC", "CI", "NEXT"] {
+        let error = Program::parse(&format!("SR -> AR\n{}: Q -> AR", label)).unwrap_err();
+        assert_eq!(error.line, 2);
+        assert!(matches!(error.kind, ProgramErrorKind::ReservedLabel(_)));
+    }
+}
+
+#[test]
+fn test_listing() {
+    let program = Program::parse(
+        "ORG 0A0H\n\
+         FETCH: PC -> AR, PC + 1 -> PC\n\
+         \x20      MEM -> DR, NEXT=FETCH, CC#=0\n",
+    )
+    .unwrap();
+    let listing = program.listing(ListingFormat::Text);
+    let lines = listing.lines().collect::<Vec<_>>();
+    assert!(lines[0].starts_with("0A0  000E00A0355402  0000000000 00 1110 000"));
+    assert!(lines[0].ends_with("FETCH: PC -> AR, PC + 1 -> PC"));
+    assert!(lines[3].contains("下地址=FETCH(0A0H)"));
+
+    let listing = program.listing(ListingFormat::Markdown);
+    let lines = listing.lines().collect::<Vec<_>>();
+    assert_eq!(lines.len(), 4);
+    assert!(lines[3].starts_with("|0A1H|`28030030F00008`|"));
+    assert!(lines[3].contains("|`MEM -> DR, NEXT=FETCH, CC#=0`|下地址=FETCH(0A0H)<br>CI=0011"));
+}
+
+#[test]
+fn test_control_flow() {
+    let program = Program::parse(
+        "FETCH: PC -> AR, PC + 1 -> PC\n\
+         \x20      MEM -> IR, CI=JMAP\n\
+         ADD:   SR + DR -> DR, NEXT=FETCH, CC#=0\n\
+         JR:    , CC#=C, NEXT=TAKEN\n\
+         \x20      , CI=CJS, CC#=/Z, NEXT=0x3F\n\
+         TAKEN: PC -> AR, NEXT=FETCH, CC#=0\n",
+    )
+    .unwrap();
+    let edges = |flow: &ControlFlow| {
+        flow.edges
+            .iter()
+            .map(|edge| (edge.from, edge.to, edge.kind))
+            .collect::<Vec<_>>()
+    };
+    let flow = ControlFlow::new(&program, &BTreeMap::from([(0x20, 2), (0x41, 3)]));
+    assert_eq!(
+        edges(&flow),
+        vec![
+            (0, Some(1), EdgeKind::Sequential),
+            (1, Some(2), EdgeKind::Dispatch),
+            (1, Some(3), EdgeKind::Dispatch),
+            (2, Some(0), EdgeKind::Jump),
+            (3, Some(5), EdgeKind::Dispatch),
+            (3, Some(4), EdgeKind::Sequential),
+            (4, Some(0x3F), EdgeKind::Call),
+            (4, Some(5), EdgeKind::Sequential),
+            (5, Some(0), EdgeKind::Jump),
+        ]
+    );
+    let dot = flow.to_dot();
+    assert!(dot.contains("    n000 [label=\"000H FETCH:\\nPC -> AR, PC + 1 -> PC\"];\n"));
+    assert!(dot.contains("    n03F [label=\"03FH (空)\", style=dashed];\n"));
+    assert!(dot.contains("    n003 -> n005 [label=\"IR10-8\", color=red, style=bold];\n"));
+
+    // 没有映射时JMAP指向映射ROM
+    let flow = ControlFlow::new(&program, &BTreeMap::new());
+    assert_eq!(edges(&flow)[1], (1, None, EdgeKind::Dispatch));
+    let mermaid = flow.to_mermaid();
+    assert!(
+        mermaid.starts_with("flowchart TD\n    n000[\"000H FETCH:<br/>PC -> AR, PC + 1 -> PC\"]\n")
+    );
+    assert!(mermaid.contains("    n001 ==>|\"JMAP\"| jmap\n"));
+    assert!(mermaid.contains("    n004 -.->|\"CALL\"| n03F\n"));
+}
+
+#[test]
+fn test_analyze() {
+    let program = Program::parse(
+        "FETCH: PC -> AR, PC + 1 -> PC\n\
+         \x20      MEM -> IR, CI=JMAP\n\
+         ADD:   SR + DR -> DR, NEXT=FETCH, CC#=0\n\
+         JZ:    , CC#=/Z, NEXT=0x30\n\
+         \x20      PC -> AR\n\
+         ORG 10H\n\
+         DEAD:  SR -> DR, NEXT=FETCH, CC#=0\n\
+         ORG 0\n\
+         \x20      Q -> Q\n",
+    )
+    .unwrap();
+    let warnings = program.analyze(&[0], &BTreeMap::from([(0x20, 2), (0x41, 3)]));
+    let kinds = warnings
+        .iter()
+        .map(|warning| (warning.line, warning.kind.clone()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        kinds,
+        vec![
+            (4, WarningKind::EmptyTarget(0x30)),
+            (5, WarningKind::FallThrough(5)),
+            (7, WarningKind::Unreachable),
+            (9, WarningKind::AddressOverlap(0, 1)),
+        ]
+    );
+    assert_eq!(warnings[0].span, (10, 19));
+
+    // 没有映射时带标号的微指令都视为入口
+    let warnings = program.analyze(&[0], &BTreeMap::new());
+    assert!(
+        warnings
+            .iter()
+            .all(|warning| warning.kind != WarningKind::Unreachable)
+    );
+}
+
+#[test]
+fn test_dataflow() {
+    let program = Program::parse(
+        "FETCH: PC -> AR\n\
+         \x20      MEM -> IR, CI=JMAP\n\
+         ADD:   MEM -> DR, NEXT=FETCH, CC#=0\n\
+         SUB:   Q -> R1\n\
+         \x20      , PC + 1 -> PC\n\
+         \x20      R1 -> Q\n\
+         \x20      Q -> R3, NEXT=FETCH, CC#=0\n\
+         JR:    , CC#=S, NEXT=FETCH, CI=JRP\n",
+    )
+    .unwrap();
+    let map = BTreeMap::from([(0x20, 2), (0x21, 3)]);
+    let kinds = |entries: &[u16]| {
+        program
+            .dataflow(entries, &map)
+            .into_iter()
+            .map(|warning| (warning.line, warning.kind, warning.span))
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(
+        kinds(&[0]),
+        vec![
+            (2, WarningKind::PcNotIncremented, (0, 9)),
+            (3, WarningKind::MemWithoutAr, (0, 9)),
+            (4, WarningKind::QBeforeWrite, (0, 7)),
+            (5, WarningKind::PcStepWithoutFetch, (2, 14)),
+        ]
+    );
+    // JR没有前驱，作为入口时IR中没有指令
+    assert_eq!(
+        kinds(&[0, 7]).last(),
+        Some(&(8, WarningKind::ConditionWithoutIr, (2, 7)))
+    );
+}
+
+#[test]
+fn test_decode() {
+    let decoder = Decoder::from_hex("0000 0E00 90B0 008A").unwrap();
+    assert_eq!(decoder.instrument(&Instrument::CI([0; 4])).value(), 14);
+    assert_eq!(decoder.instrument(&Instrument::MI20([0; 3])).value(), 3);
+    assert_eq!(decoder.instrument(&Instrument::DC2([0; 3])).value(), 2);
+    assert_eq!(decoder.mem(), MEM::NONE);
+    assert_eq!(
+        hex::encode_upper(Decoder::from_hex("000E0010E00002").unwrap().hex()),
+        "000E0010E00002"
+    );
+    assert!(Decoder::from_hex("1000E0010E00002").is_err());
+
+    // 按encode的--padding和--order还原
+    let hex = |input: &str, format: WordFormat| {
+        Decoder::from_words(input, &format, Arc::new(Layout::tec2()))
+            .map(|decoder| hex::encode_upper(decoder.hex()))
+    };
+    let right = WordFormat::new(Padding::Right, WordOrder::HighFirst);
+    assert_eq!(hex("000E 0090 4000 8200", right).unwrap(), "000E0090400082");
+    assert_eq!(
+        hex(
+            "8200 4000 0090 000E",
+            WordFormat::new(Padding::Right, WordOrder::LowFirst)
+        )
+        .unwrap(),
+        "000E0090400082"
+    );
+    assert_eq!(
+        hex(
+            "0082 9040 0E00 0000",
+            WordFormat::new(Padding::Left, WordOrder::LowFirst)
+        )
+        .unwrap(),
+        "000E0090400082"
+    );
+    assert_eq!(hex("000E0090400082", right).unwrap(), "000E0090400082");
+    // 补的0不为0
+    assert!(hex("000E 0090 4000 8201", right).is_err());
+    assert!(hex("010E 0090 4000 8200", WordFormat::default()).is_err());
+}
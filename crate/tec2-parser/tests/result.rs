@@ -0,0 +1,123 @@
+use serde_json::json;
+use tec2_parser::result::{FieldState, ParseResult};
+use tec2_parser::{Error, Lint, Severity};
+
+#[test]
+fn test_result() {
+    let result = ParseResult::parse("Q -> AR, CarryFromALU");
+    let ParseResult::Result(encoding) = &result else {
+        panic!("{}", result);
+    };
+    assert_eq!(hex::encode_upper(&encoding.hex), "000E0190200002");
+    assert_eq!(encoding.bin.len(), 56);
+    let state = |name: &str| {
+        encoding
+            .fields
+            .iter()
+            .find(|field| field.name == name)
+            .unwrap()
+            .state
+    };
+    assert_eq!(state("SST"), FieldState::Set);
+    assert_eq!(state("MI2-0"), FieldState::Set);
+    assert_eq!(state("CI"), FieldState::Default);
+    assert_eq!(state("A口"), FieldState::DontCare);
+
+    let report = serde_json::to_value(result.report("Q -> AR, CarryFromALU")).unwrap();
+    assert_eq!(report["ok"], json!(true));
+    assert_eq!(report["kind"], json!("result"));
+    assert_eq!(report["hex"], json!("000E0190200002"));
+    assert_eq!(report["fields"][4]["name"], json!("SST"));
+    assert_eq!(report["fields"][4]["value"], json!(1));
+}
+
+#[test]
+fn test_error() {
+    let result = ParseResult::parse("Q + Q -> AR");
+    let report = serde_json::to_value(result.report("Q + Q -> AR")).unwrap();
+    assert_eq!(report["ok"], json!(false));
+    assert_eq!(report["kind"], json!("expr_parse_error"));
+    assert_eq!(report["error"]["kind"], json!("can_not_be_achieved"));
+    assert_eq!(report["error"]["detail"], json!("LeftRightCanNotBeSame"));
+    assert_eq!(report["span"], json!([0, 11]));
+
+    let result = ParseResult::parse("MEM + + Q");
+    assert_eq!(result.span(), Some((6, 7)));
+    let report = serde_json::to_value(result.report("MEM + + Q")).unwrap();
+    assert_eq!(report["kind"], json!("unrecognized_token"));
+    assert!(report["message"].is_string());
+
+    // 地址超出16位时不能截断
+    assert!(matches!(
+        ParseResult::parse("Q -> AR, NEXT=0x10000"),
+        ParseResult::ExprParseError {
+            error: Error::AddressOutOfRange(0x10000)
+        }
+    ));
+    assert!(matches!(
+        ParseResult::parse(", CC#=2"),
+        ParseResult::ExprParseError {
+            error: Error::InvalidCondition(_)
+        }
+    ));
+    assert!(ParseResult::parse(", CC#=1").is_ok());
+
+    // 还不支持的写法也要给出错误
+    for input in ["PC + Q -> AR", "Q -> IP"] {
+        let result = ParseResult::parse(input);
+        assert_eq!(
+            serde_json::to_value(result.report(input)).unwrap()["error"]["kind"],
+            json!("can_not_be_achieved")
+        );
+    }
+}
+
+#[test]
+fn test_diagnostics() {
+    // 字段冲突时无法编码，位置为冲突的子表达式
+    let input = "SR -> R2, PC + 1 -> PC";
+    let result = ParseResult::parse(input);
+    assert!(!result.is_ok());
+    let diagnostics = result.diagnostics(input);
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0].severity, Severity::Error);
+    assert_eq!(diagnostics[0].span, (10, 22));
+    let ParseResult::ExprParseError {
+        error: Error::FieldConflict { field, .. },
+    } = ParseResult::parse("MEM -> MEM")
+    else {
+        panic!();
+    };
+    assert_eq!(field, "WE");
+    assert_eq!(ParseResult::parse("MEM -> MEM").span(), Some((7, 10)));
+
+    let input = "MEM -> IR, CC#=Z, CI=IF, NEXT=100";
+    let diagnostics = ParseResult::parse(input).diagnostics(input);
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0].lint, Some(Lint::IrNotLoaded));
+    assert_eq!(diagnostics[0].span, (11, 16));
+
+    let input = "R5 -> AR, NEXT=10";
+    let diagnostics = ParseResult::parse(input).diagnostics(input);
+    assert_eq!(diagnostics[0].severity, Severity::Note);
+    assert_eq!(diagnostics[0].lint, Some(Lint::RegisterAsPc));
+    assert_eq!(diagnostics[0].span, (0, 8));
+    // 普通的寄存器传送没有提示
+    for input in ["DR -> MEM", "SR -> AR", "R1 -> MEM", "Q -> AR", "R1 -> R2"] {
+        assert!(ParseResult::parse(input).diagnostics(input).is_empty());
+    }
+    assert!(
+        ParseResult::parse("PC -> AR, PC + 1 -> PC")
+            .diagnostics("")
+            .is_empty()
+    );
+
+    let report = serde_json::to_value(result.report("SR -> R2, PC + 1 -> PC")).unwrap();
+    assert_eq!(report["diagnostics"][0]["severity"], json!("error"));
+    assert_eq!(report["error"]["kind"], json!("field_conflict"));
+    assert_eq!(report["span"], json!([10, 22]));
+    let result = ParseResult::parse("Q + Q -> AR");
+    let diagnostics = result.diagnostics("Q + Q -> AR");
+    assert_eq!(diagnostics[0].severity, Severity::Error);
+    assert_eq!(diagnostics[0].lint, None);
+}
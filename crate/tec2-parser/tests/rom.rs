@@ -0,0 +1,233 @@
+use std::collections::BTreeMap;
+use tec2_parser::parser::format::WordOrder;
+use tec2_parser::parser::layout::Layout;
+use tec2_parser::program::{
+    ChipSlicing, Decompiled, ImageError, ImageFormat, MemoryFormat, Program, RomError, intel_hex,
+    markdown_tables,
+};
+
+fn image() -> BTreeMap<u16, Vec<u8>> {
+    BTreeMap::from([(1, vec![0x12, 0x34, 0x56, 0x78, 0x9A, 0xBC, 0xDE])])
+}
+
+#[test]
+fn test_slice() {
+    let layout = Layout::tec2();
+    let mut slicing = ChipSlicing {
+        range: 0..=2,
+        ..ChipSlicing::new(&layout)
+    };
+    let chips = slicing.slice(&image(), &layout).unwrap();
+    assert_eq!(chips.len(), 7);
+    assert_eq!(chips[0].data, vec![0, 0x12, 0]);
+    assert_eq!(chips[6].data, vec![0, 0xDE, 0]);
+    assert_eq!(chips[0].to_bin(), vec![0, 0x12, 0]);
+
+    slicing.width = 16;
+    slicing.order = WordOrder::LowFirst;
+    let chips = slicing.slice(&image(), &layout).unwrap();
+    assert_eq!(chips.len(), 4);
+    assert_eq!((chips[0].begin, chips[0].width), (48, 8));
+    assert_eq!(chips[0].data[1], 0xDE);
+    assert_eq!(chips[3].data[1], 0x1234);
+    assert_eq!(chips[3].to_bin(), vec![0, 0, 0x12, 0x34, 0, 0]);
+
+    slicing.width = 8;
+    slicing.order = WordOrder::HighFirst;
+    slicing.reverse = true;
+    assert_eq!(slicing.slice(&image(), &layout).unwrap()[0].data[1], 0x48);
+
+    slicing.width = 17;
+    assert_eq!(
+        slicing.slice(&image(), &layout).unwrap_err(),
+        RomError::InvalidChipWidth(17)
+    );
+    slicing.width = 8;
+    slicing.range = 0..=0x400;
+    assert_eq!(
+        slicing.slice(&image(), &layout).unwrap_err(),
+        RomError::InvalidRange(0, 0x400)
+    );
+}
+
+#[test]
+fn test_intel_hex() {
+    assert_eq!(intel_hex(0, &[0x12]), ":0100000012ED\n:00000001FF\n");
+    let hex = intel_hex(0xFFF0, &[0; 32]);
+    assert_eq!(hex.lines().nth(1), Some(":020000040001F9"));
+}
+
+#[test]
+fn test_memory_format() {
+    let layout = Layout::tec2();
+    let readmemh = MemoryFormat::ReadMemH
+        .export(&image(), &layout, 0xFF)
+        .unwrap();
+    assert_eq!(readmemh.lines().count(), 1024);
+    assert_eq!(
+        readmemh.lines().take(2).collect::<Vec<_>>(),
+        vec!["000000000000FF", "123456789ABCDE"]
+    );
+    let readmemb = MemoryFormat::ReadMemB.export(&image(), &layout, 0).unwrap();
+    assert_eq!(readmemb.lines().nth(1).unwrap().len(), 56);
+
+    let coe = MemoryFormat::Coe.export(&image(), &layout, 0).unwrap();
+    assert!(coe.starts_with(
+        "memory_initialization_radix=16;\nmemory_initialization_vector=\n00000000000000,\n123456789ABCDE,\n"
+    ));
+    assert!(coe.ends_with("00000000000000;\n"));
+
+    let mif = MemoryFormat::Mif.export(&image(), &layout, 0).unwrap();
+    assert!(mif.starts_with("WIDTH=56;\nDEPTH=1024;\n"));
+    assert!(mif.contains("\t001 : 123456789ABCDE;\n"));
+    assert!(mif.ends_with("\t3FF : 00000000000000;\nEND;\n"));
+
+    let logisim = MemoryFormat::Logisim.export(&image(), &layout, 0).unwrap();
+    assert_eq!(logisim, "v2.0 raw\n0 123456789abcde 1022*0\n");
+
+    assert_eq!(
+        MemoryFormat::Coe
+            .export(&image(), &layout, 1 << 56)
+            .unwrap_err(),
+        RomError::InvalidFill(1 << 56)
+    );
+}
+
+const PROGRAM: &str = "FETCH: PC -> AR, PC + 1 -> PC\n\
+                       \x20      MEM -> IR\n\
+                       \x20      , CI=JMAP\n\
+                       ADD:   SR + DR -> DR, CarryFromALU, NEXT=FETCH, CC#=0\n\
+                       SUB:   DR - SR -> DR, CarryFromALU, CC#=/Z, NEXT=ADD\n\
+                       \x20      MEM - Q -> Q\n\
+                       \x20      PC -> AR\n\
+                       \x20      DR -> MEM, NEXT=FETCH, CC#=0\n\
+                       \x20      R3 -> R3, CI=CJS, CC#=C, NEXT=SUB\n";
+
+#[test]
+fn test_decompile() {
+    let image = Program::parse(PROGRAM).unwrap().image().unwrap();
+    let decompiled = Decompiled::new(&image);
+    assert!(decompiled.words().iter().all(|word| word.expr.is_some()));
+    assert_eq!(
+        decompiled.words()[3].expr.as_deref(),
+        Some("SR + DR -> DR, CarryFromALU, CC#=0, NEXT=L000")
+    );
+    assert_eq!(decompiled.words()[0].label.as_deref(), Some("L000"));
+
+    // 还原的微程序重新编译后和原来一致
+    let source = decompiled.source();
+    assert_eq!(Program::parse(&source).unwrap().image().unwrap(), image);
+
+    // 移位无法用表达式表示
+    let mut image = image;
+    image.insert(0x10, vec![0x00, 0x00, 0x00, 0x00, 0x00, 0x80, 0x00]);
+    let decompiled = Decompiled::new(&image);
+    let source = decompiled.source();
+    assert!(source.contains("; 010: 00000000008000 无法还原为表达式"));
+}
+
+#[test]
+fn test_import() {
+    let layout = Layout::tec2();
+    let image = Program::parse(PROGRAM).unwrap().image().unwrap();
+    let mif = MemoryFormat::Mif.export(&image, &layout, 0).unwrap();
+    let imported = ImageFormat::detect(Some("mif"), mif.as_bytes());
+    assert_eq!(imported, ImageFormat::Mif);
+    let imported = imported.import(mif.as_bytes(), &layout).unwrap();
+    assert_eq!(imported.len(), 1024);
+    assert_eq!(imported[&4], image[&4]);
+
+    let binary = image.values().flatten().copied().collect::<Vec<_>>();
+    let imported = ImageFormat::Binary.import(&binary, &layout).unwrap();
+    assert_eq!(imported, image);
+    assert_eq!(
+        ImageFormat::Binary.import(&binary[1..], &layout),
+        Err(ImageError::InvalidLength(binary.len() - 1))
+    );
+
+    let hex = intel_hex(0, &binary);
+    assert_eq!(
+        ImageFormat::detect(None, hex.as_bytes()),
+        ImageFormat::IntelHex
+    );
+    assert_eq!(
+        ImageFormat::IntelHex
+            .import(hex.as_bytes(), &layout)
+            .unwrap(),
+        image
+    );
+    let broken = hex.replacen(":10", ":11", 1);
+    assert!(matches!(
+        ImageFormat::IntelHex.import(broken.as_bytes(), &layout),
+        Err(ImageError::InvalidRecord(1))
+    ));
+
+    let text = "; 注释\n002: 0000 0E00 90B0 008A\n@10\n00000000000001\n";
+    let imported = ImageFormat::Text.import(text.as_bytes(), &layout).unwrap();
+    assert_eq!(imported.keys().copied().collect::<Vec<_>>(), vec![2, 0x10]);
+    assert_eq!(imported[&0x10], vec![0, 0, 0, 0, 0, 0, 1]);
+}
+
+#[test]
+fn test_csv() {
+    let layout = Layout::tec2();
+    let program = Program::parse(PROGRAM).unwrap();
+    let csv = program.to_csv();
+    assert!(csv.starts_with("地址,标号,下地址,CI,SCC,SC,SST,MIO,MI8-6,"));
+    assert!(csv.contains(",\"SR + DR -> DR, CarryFromALU, NEXT=FETCH, CC#=0\"\n"));
+    let image = ImageFormat::Csv.import(csv.as_bytes(), &layout).unwrap();
+    assert_eq!(image, program.image().unwrap());
+
+    // 没有地址列时依次排列，未列出的字段为0，也可以写十六进制
+    let csv = "\u{feff}CI,备用,MI86,A口,B口\n1110,,010,5H,0x5\n0010,1,,,\n";
+    let image = ImageFormat::Csv.import(csv.as_bytes(), &layout).unwrap();
+    assert_eq!(hex::encode_upper(&image[&0]), "000E0020055000");
+    assert_eq!(hex::encode_upper(&image[&1]), "00020000000000");
+
+    assert_eq!(
+        ImageFormat::Csv.import("地址,CI\n0,10000\n".as_bytes(), &layout),
+        Err(ImageError::InvalidField(
+            2,
+            "CI".to_string(),
+            "10000".to_string()
+        ))
+    );
+}
+
+#[test]
+fn test_markdown_table() {
+    let layout = Layout::tec2();
+    let report = "# 实验报告\n\n\
+        |下地址|备用|CI|SCC|SC|备用|SST|MIO|MI8-6|REQ|MI5-3|WE|MI2-0|A口|B口|SCi|SSH|SA|DC1|SB|DC2|\n\
+        |-|-|-|-|-|-|-|-|-|-|-|-|-|-|-|-|-|-|-|-|-|\n\
+        |0000000000|00|1110|000|0|0|000|1|001|0|000|0|011|0000|0000|00|00|1|000|1|010|\n\n\
+        |A|B|\n|:-:|:-:|\n|1|2|\n\n\
+        |下地址|备用|CI|SCC|SC|备用|SST|MIO|MI8-6|REQ|MI5-3|WE|MI2-0|A口|B口|SCi|SSH|SA|DC1|SB|DC2|\n\
+        |-|-|-|-|-|-|-|-|-|-|-|-|-|-|-|-|-|-|-|-|-|\n\
+        |0000000000|00|0010|000|0|0|000|0|001|0|000|1|111|0000|0000|00|00|0|000|0|001|\n\
+        |0000000000|00|0010|000|0|0|000|0|001|0|000|1|111|0000|0000|00|00|0|000|0|1001|\n";
+    assert_eq!(
+        markdown_tables(report, &layout),
+        Err(ImageError::InvalidField(
+            14,
+            "DC2".to_string(),
+            "1001".to_string()
+        ))
+    );
+
+    let report = report.rsplit_once("\n|0000").unwrap().0;
+    let rows = markdown_tables(report, &layout).unwrap();
+    assert_eq!(rows.len(), 2);
+    assert_eq!(rows[0].line, 5);
+    assert_eq!(hex::encode_upper(&rows[0].hex), "000E009030008A");
+    assert_eq!(hex::encode_upper(&rows[1].hex), "00020010F00001");
+
+    assert_eq!(
+        ImageFormat::detect(None, report.as_bytes()),
+        ImageFormat::Markdown
+    );
+    let image = ImageFormat::Markdown
+        .import(report.as_bytes(), &layout)
+        .unwrap();
+    assert_eq!(image.keys().copied().collect::<Vec<_>>(), vec![0, 1]);
+}
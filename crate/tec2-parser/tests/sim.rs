@@ -0,0 +1,139 @@
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use tec2_parser::ast::token::Condition;
+use tec2_parser::grammar;
+use tec2_parser::parser::decoder::Decoder;
+use tec2_parser::parser::instrument::{Ci, Instrument, ToInstrument};
+use tec2_parser::parser::layout::Layout;
+use tec2_parser::parser::parser::ExprParser;
+use tec2_parser::program::Program;
+use tec2_parser::sim::{Datapath, Flags, Machine, Sequencer, Signals, SimError};
+
+fn word(input: &str) -> Decoder {
+    let mut parser = ExprParser::new(grammar::ExprParser::new().parse(input).unwrap());
+    parser.parse().unwrap();
+    Decoder::new(&parser.hex())
+}
+
+#[test]
+fn test_datapath() {
+    let mut state = Datapath::new();
+    state.q = 3;
+
+    // 减法的最低位进位为1，R-S=R+/S+1
+    let (next, output) = state.step(&word("MEM - Q -> Q, CarryFromALU"), 10);
+    assert_eq!((output.r, output.s), (10, 3));
+    assert_eq!(next.q, 7);
+    assert_eq!(
+        next.flags,
+        Flags {
+            c: true,
+            z: false,
+            v: false,
+            s: false
+        }
+    );
+
+    state.registers[5] = 0x00A0;
+    let (next, output) = state.step(&word("PC -> AR, PC + 1 -> PC"), 0);
+    assert_eq!(output.y, 0x00A0);
+    assert_eq!(next.registers[5], 0x00A1);
+    assert_eq!(next.flags, Flags::default());
+
+    state.ir = 0x0021; // DR=R2, SR=R1
+    state.registers[1] = 0x8000;
+    state.registers[2] = 0x8000;
+    let (next, output) = state.step(&word("SR + DR -> DR, CarryFromALU"), 0);
+    assert_eq!(output.f, 0);
+    assert_eq!(next.registers[2], 0);
+    assert!(next.flags.c && next.flags.z && next.flags.v && !next.flags.s);
+}
+
+fn encode(ci: Ci, next: u16, pass: bool) -> Vec<u8> {
+    let mut instruments = ci.to_instrument().to_vec();
+    instruments.push(Instrument::NEXT([0; 10]).with_value(next));
+    instruments.push(Instrument::SCC([0; 3]).with_value(!pass as u16));
+    Layout::active().encode(instruments.iter())
+}
+
+#[test]
+fn test_sequencer() {
+    let store = BTreeMap::from([
+        (0x000, encode(Ci::CJS, 0x10, true)),
+        (0x010, encode(Ci::LDCT, 2, true)),
+        (0x011, encode(Ci::PUSH, 0, false)),
+        (0x012, encode(Ci::RFCT, 0, true)),
+        (0x013, encode(Ci::CRTN, 0, true)),
+        (0x001, encode(Ci::IF, 0x20, false)),
+    ]);
+    let trace = Sequencer::new(0).trace(&store, &mut Datapath::new(), &Signals::default(), 100);
+    assert_eq!(
+        trace.addresses,
+        vec![
+            0x000, 0x010, 0x011, 0x012, 0x012, 0x012, 0x013, 0x001, 0x002
+        ]
+    );
+    assert_eq!(trace.error, Some(SimError::EmptyAddress(0x002)));
+
+    // 控存的最后一个地址之后回到0
+    assert_eq!(Sequencer::new(0x3FF).upc, 0);
+    let mut sequencer = Sequencer::new(0);
+    sequencer.jump(0xFFFF);
+    assert_eq!(sequencer.upc, 0);
+    // 8位下地址的格式在FFH之后回到0，和当前使用的格式无关
+    let mut layout = Layout::tec2();
+    layout.fields[0].width = 8;
+    let mut sequencer = Sequencer::with_layout(0xFF, Arc::new(layout));
+    assert_eq!(sequencer.upc, 0);
+    sequencer.jump(0x1FE);
+    assert_eq!(sequencer.upc, 0xFF);
+
+    let mut datapath = Datapath::new();
+    datapath.ir = 0x4700; // JRNZ
+    assert!(Sequencer::pass(
+        &Condition::IR108,
+        &datapath,
+        &Signals::default()
+    ));
+    datapath.flags.z = true;
+    assert!(!Sequencer::pass(
+        &Condition::IR108,
+        &datapath,
+        &Signals::default()
+    ));
+}
+
+#[test]
+fn test_machine() {
+    let program = Program::parse(
+        "FETCH: PC -> AR, PC + 1 -> PC\n\
+         \x20      MEM -> IR\n\
+         \x20      , CI=JMAP\n\
+         MVRD:  PC -> AR, PC + 1 -> PC\n\
+         \x20      MEM -> DR, NEXT=FETCH, CC#=0\n\
+         ADD:   SR + DR -> DR, CarryFromALU, NEXT=FETCH, CC#=0\n\
+         STO:   PC -> AR, PC + 1 -> PC\n\
+         \x20      MEM -> AR\n\
+         \x20      DR -> MEM, NEXT=FETCH, CC#=0\n",
+    )
+    .unwrap();
+    let map = [(0x01, "MVRD"), (0x02, "ADD"), (0x03, "STO")]
+        .into_iter()
+        .map(|(opcode, label)| (opcode, program.label(label).unwrap()))
+        .collect();
+    let mut machine = Machine::new(program.image().unwrap(), map);
+    // MVRD R1, 5; MVRD R2, 7; ADD R2, R1; STO R2, [100H]
+    machine.load(0, &[0x0110, 5, 0x0120, 7, 0x0221, 0x0320, 0x0100]);
+    machine
+        .run(1000, |machine| {
+            machine.pc() == 7 && machine.sequencer.address == 0
+        })
+        .unwrap();
+    assert_eq!(machine.datapath.registers[2], 12);
+    assert_eq!(machine.memory[0x100], 12);
+    assert_eq!(machine.cycles, 20);
+
+    // 主存7中为0，没有对应的微程序入口
+    assert_eq!(machine.step(100).unwrap_err(), SimError::UnmappedOpcode(0));
+    assert_eq!(machine.datapath.ir, 0);
+}